@@ -0,0 +1,446 @@
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use fontdue::layout::{HorizontalAlign, VerticalAlign};
+
+use crate::color::Color;
+use crate::config::PhotoFormat;
+use crate::ui::{ButtonStyle, UI, UIElement};
+
+/// Wrong PINs accepted before the menu closes and is locked
+const MAX_PIN_ATTEMPTS: u32 = 3;
+/// How long the menu can't be opened after too many wrong PINs. Every wrong
+/// PIN after that locks it again, until the right one is entered
+const PIN_LOCKOUT_TIME: Duration = Duration::from_secs(5 * 60);
+const MAX_COUNTDOWN: u32 = 30;
+const KEYBOARD_ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl'", "zxcvbnm,.!?"];
+const BACKDROP_COLOR: Color = Color::from_argb(0xE0000000);
+
+/// A change made in the admin menu, for the application to apply and save
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminRequest {
+    SetCountdown(u32),
+    SetPhotoFormat(PhotoFormat),
+    /// Set the text called `key` in the configuration
+    SetText { key: &'static str, text: String },
+    /// Save photos on the USB device with this name
+    SelectStorage(String),
+    Eject,
+    Shutdown,
+    Calibrate,
+    Close,
+    /// A wrong PIN was entered, see `PinAttempts`
+    WrongPin,
+    /// The right PIN was entered
+    PinAccepted,
+}
+
+/// Wrong PINs entered in the admin menu. Kept by the application rather than
+/// the menu, so closing and opening the menu again doesn't reset them
+#[derive(Debug, Default)]
+pub struct PinAttempts {
+    failed: u32,
+    locked_until: Option<Instant>,
+}
+
+impl PinAttempts {
+    /// Whether the menu can't be opened at `now`
+    pub fn is_locked(&self, now: Instant) -> bool {
+        self.locked_until.is_some_and(|until| now < until)
+    }
+
+    /// Count a wrong PIN entered at `now`. Returns true when the menu is
+    /// locked by it
+    pub fn failed(&mut self, now: Instant) -> bool {
+        self.failed += 1;
+        if self.failed < MAX_PIN_ATTEMPTS {
+            return false;
+        }
+        self.locked_until = Some(now + PIN_LOCKOUT_TIME);
+        true
+    }
+
+    pub fn succeeded(&mut self) {
+        *self = PinAttempts::default();
+    }
+}
+
+/// What the admin menu shows about the booth
+#[derive(Debug, Clone, Default)]
+pub struct AdminStatus {
+    pub storage: String,
+    pub photo_count: usize,
+    pub camera: String,
+    /// The last lines of the log file
+    pub log: Vec<String>,
+}
+
+/// Current values of the settings that can be changed in the admin menu
+#[derive(Debug, Clone, Default)]
+pub struct AdminSettings {
+    pub countdown: u32,
+    pub photo_format: PhotoFormat,
+    /// Texts in the language shown, by key in the configuration
+    pub texts: Vec<(&'static str, String)>,
+    /// Names of the connected USB devices
+    pub storage_devices: Vec<String>,
+    /// The device photos are saved on
+    pub storage_device: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Event {
+    Digit(char),
+    ErasePin,
+    SubmitPin,
+    CountdownDown,
+    CountdownUp,
+    NextFormat,
+    NextText,
+    EditText,
+    NextStorage,
+    Eject,
+    Shutdown,
+    Calibrate,
+    Close,
+    Key(char),
+    Erase,
+    Shift,
+    Done,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Page {
+    Pin,
+    Menu,
+    Keyboard,
+}
+
+/// A rectangle as parts of the screen size: x, y, width, height
+type Rect = (f32, f32, f32, f32);
+
+/// Settings and status of the booth for the operator, behind a PIN
+pub struct AdminMenu {
+    pin: String,
+    entered_pin: String,
+    page: Page,
+    settings: AdminSettings,
+    status: AdminStatus,
+    /// Index into the texts of the settings of the text shown and edited
+    text_index: usize,
+    edited_text: String,
+    shift: bool,
+    /// The shutdown button was touched once, touching it again shuts down
+    confirm_shutdown: bool,
+    message: Option<String>,
+    screen_size: (f32, f32),
+    event_sender: Sender<Event>,
+    event_receiver: Receiver<Event>,
+}
+
+impl AdminMenu {
+    pub fn new(pin: String, settings: AdminSettings, status: AdminStatus, screen_size: (usize, usize)) -> Self {
+        let (event_sender, event_receiver) = std::sync::mpsc::channel();
+        AdminMenu {
+            pin,
+            entered_pin: String::new(),
+            page: Page::Pin,
+            settings,
+            status,
+            text_index: 0,
+            edited_text: String::new(),
+            shift: false,
+            confirm_shutdown: false,
+            message: None,
+            screen_size: (screen_size.0 as f32, screen_size.1 as f32),
+            event_sender,
+            event_receiver,
+        }
+    }
+
+    /// Show new values after the application applied a change
+    pub fn refresh(&mut self, settings: AdminSettings, status: AdminStatus) {
+        self.settings = settings;
+        self.status = status;
+    }
+
+    /// Show `message` below the settings, e.g. the result of an action
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Handle touches on the menu. Returns the changes the application should
+    /// make. Call `build` afterwards when any were returned
+    pub fn update(&mut self, ui: &mut UI) -> Vec<AdminRequest> {
+        let mut requests = Vec::new();
+        let mut changed = false;
+        while let Ok(event) = self.event_receiver.try_recv() {
+            self.handle(event, &mut requests);
+            changed = true;
+        }
+        if changed && requests.is_empty() {
+            self.build(ui);
+        }
+        requests
+    }
+
+    fn handle(&mut self, event: Event, requests: &mut Vec<AdminRequest>) {
+        let confirm_shutdown = std::mem::take(&mut self.confirm_shutdown);
+        if self.page != Page::Pin {
+            self.message = None;
+        }
+
+        match event {
+            Event::Digit(digit) => self.entered_pin.push(digit),
+            Event::ErasePin => _ = self.entered_pin.pop(),
+            Event::SubmitPin => {
+                if self.entered_pin == self.pin {
+                    self.page = Page::Menu;
+                    self.message = None;
+                    requests.push(AdminRequest::PinAccepted);
+                } else {
+                    self.message = Some("Wrong PIN".to_string());
+                    requests.push(AdminRequest::WrongPin);
+                }
+                self.entered_pin.clear();
+            },
+            Event::CountdownDown if self.settings.countdown > 1 => {
+                self.settings.countdown -= 1;
+                requests.push(AdminRequest::SetCountdown(self.settings.countdown));
+            },
+            Event::CountdownUp if self.settings.countdown < MAX_COUNTDOWN => {
+                self.settings.countdown += 1;
+                requests.push(AdminRequest::SetCountdown(self.settings.countdown));
+            },
+            Event::CountdownDown | Event::CountdownUp => {},
+            Event::NextFormat => {
+                self.settings.photo_format = match self.settings.photo_format {
+                    PhotoFormat::Jpeg => PhotoFormat::Png,
+                    PhotoFormat::Png => PhotoFormat::Jpeg,
+                };
+                requests.push(AdminRequest::SetPhotoFormat(self.settings.photo_format));
+            },
+            Event::NextText => self.text_index = (self.text_index + 1) % self.settings.texts.len().max(1),
+            Event::EditText => {
+                if let Some((_, text)) = self.settings.texts.get(self.text_index) {
+                    self.edited_text = text.clone();
+                    self.shift = false;
+                    self.page = Page::Keyboard;
+                }
+            },
+            Event::NextStorage => {
+                let devices = &self.settings.storage_devices;
+                if !devices.is_empty() {
+                    let current = devices.iter().position(|device| Some(device) == self.settings.storage_device.as_ref());
+                    let next = current.map_or(0, |index| (index + 1) % devices.len());
+                    requests.push(AdminRequest::SelectStorage(devices[next].clone()));
+                }
+            },
+            Event::Eject => requests.push(AdminRequest::Eject),
+            Event::Shutdown if confirm_shutdown => requests.push(AdminRequest::Shutdown),
+            Event::Shutdown => self.confirm_shutdown = true,
+            Event::Calibrate => requests.push(AdminRequest::Calibrate),
+            Event::Close => requests.push(AdminRequest::Close),
+            Event::Key(key) => {
+                let key = if self.shift { key.to_uppercase().next().unwrap_or(key) } else { key };
+                self.edited_text.push(key);
+                self.shift = false;
+            },
+            Event::Erase => _ = self.edited_text.pop(),
+            Event::Shift => self.shift = !self.shift,
+            Event::Done => {
+                if let Some((key, text)) = self.settings.texts.get_mut(self.text_index) {
+                    *text = self.edited_text.clone();
+                    requests.push(AdminRequest::SetText { key, text: text.clone() });
+                }
+                self.page = Page::Menu;
+            },
+            Event::Cancel => self.page = Page::Menu,
+        }
+    }
+
+    /// Replace the contents of `ui` with the current page
+    pub fn build(&self, ui: &mut UI) {
+        ui.clear();
+        ui.add_panel((0., 0.), self.screen_size, BACKDROP_COLOR, 0.);
+        match self.page {
+            Page::Pin => self.build_pin(ui),
+            Page::Menu => self.build_menu(ui),
+            Page::Keyboard => self.build_keyboard(ui),
+        }
+    }
+
+    fn font_size(&self) -> f32 {
+        self.screen_size.1 * 0.04
+    }
+
+    fn small_font_size(&self) -> f32 {
+        self.screen_size.1 * 0.025
+    }
+
+    fn pixels(&self, rect: Rect) -> ((f32, f32), (f32, f32)) {
+        (
+            (rect.0 * self.screen_size.0, rect.1 * self.screen_size.1),
+            (rect.2 * self.screen_size.0, rect.3 * self.screen_size.1),
+        )
+    }
+
+    fn add_button(&self, ui: &mut UI, rect: Rect, label: &str, event: Event) {
+        let (pos, size) = self.pixels(rect);
+        let button = ui.add_button(pos, size, label, self.font_size(), ButtonStyle::default());
+        let sender = self.event_sender.clone();
+        button.borrow_mut().add_touch_listener(Box::new(move || _ = sender.send(event)));
+    }
+
+    fn add_text(&self, ui: &mut UI, rect: Rect, text: &str, font_size: f32, align: HorizontalAlign) {
+        let (pos, size) = self.pixels(rect);
+        let vertical_align = if rect.3 > 0.2 { VerticalAlign::Top } else { VerticalAlign::Middle };
+        ui.add_text_box(pos, size, align, vertical_align).borrow_mut().add_text(text, font_size);
+    }
+
+    fn build_pin(&self, ui: &mut UI) {
+        let title = self.message.as_deref().unwrap_or("Enter PIN");
+        self.add_text(ui, (0.3, 0.04, 0.4, 0.1), title, self.font_size(), HorizontalAlign::Center);
+        self.add_text(ui, (0.3, 0.14, 0.4, 0.1), &"*".repeat(self.entered_pin.len()), self.font_size(), HorizontalAlign::Center);
+
+        let keys = [
+            ("1", Event::Digit('1')), ("2", Event::Digit('2')), ("3", Event::Digit('3')),
+            ("4", Event::Digit('4')), ("5", Event::Digit('5')), ("6", Event::Digit('6')),
+            ("7", Event::Digit('7')), ("8", Event::Digit('8')), ("9", Event::Digit('9')),
+            ("Del", Event::ErasePin), ("0", Event::Digit('0')), ("OK", Event::SubmitPin),
+        ];
+        for (index, (label, event)) in keys.into_iter().enumerate() {
+            let (column, row) = ((index % 3) as f32, (index / 3) as f32);
+            self.add_button(ui, (0.33 + column * 0.115, 0.27 + row * 0.16, 0.1, 0.14), label, event);
+        }
+        self.add_button(ui, (0.02, 0.86, 0.15, 0.11), "Cancel", Event::Close);
+    }
+
+    fn build_menu(&self, ui: &mut UI) {
+        let status = &self.status;
+        let mut lines = vec![
+            format!("Storage: {}", status.storage),
+            format!("Photos: {}", status.photo_count),
+            format!("Camera: {}", status.camera),
+            String::new(),
+            "Log:".to_string(),
+        ];
+        lines.extend(status.log.iter().cloned());
+        self.add_text(ui, (0.02, 0.03, 0.46, 0.94), &lines.join("\n"), self.small_font_size(), HorizontalAlign::Left);
+
+        let row = |index: usize| 0.03 + index as f32 * 0.135;
+        let height = 0.11;
+        let label_rect = |index: usize| (0.5, row(index), 0.29, height);
+
+        let countdown = format!("Countdown: {} s", self.settings.countdown);
+        self.add_text(ui, label_rect(0), &countdown, self.font_size(), HorizontalAlign::Left);
+        self.add_button(ui, (0.8, row(0), 0.08, height), "-", Event::CountdownDown);
+        self.add_button(ui, (0.9, row(0), 0.08, height), "+", Event::CountdownUp);
+
+        let format = match self.settings.photo_format {
+            PhotoFormat::Jpeg => "JPEG",
+            PhotoFormat::Png => "PNG",
+        };
+        self.add_text(ui, label_rect(1), &format!("Format: {}", format), self.font_size(), HorizontalAlign::Left);
+        self.add_button(ui, (0.8, row(1), 0.18, height), "Change", Event::NextFormat);
+
+        if let Some((key, text)) = self.settings.texts.get(self.text_index) {
+            let label = format!("{}:\n{}", key, truncate(text, 28));
+            self.add_text(ui, label_rect(2), &label, self.small_font_size(), HorizontalAlign::Left);
+            self.add_button(ui, (0.8, row(2), 0.08, height), ">", Event::NextText);
+            self.add_button(ui, (0.9, row(2), 0.08, height), "Edit", Event::EditText);
+        }
+
+        let storage = self.settings.storage_device.as_deref().unwrap_or("none");
+        self.add_text(ui, label_rect(3), &format!("USB: {}", storage), self.font_size(), HorizontalAlign::Left);
+        self.add_button(ui, (0.8, row(3), 0.18, height), "Change", Event::NextStorage);
+
+        self.add_button(ui, (0.5, row(4), 0.23, height), "Eject USB", Event::Eject);
+        self.add_button(ui, (0.75, row(4), 0.23, height), "Calibrate", Event::Calibrate);
+
+        let shutdown = if self.confirm_shutdown { "Sure?" } else { "Shut down" };
+        self.add_button(ui, (0.5, row(5), 0.23, height), shutdown, Event::Shutdown);
+        self.add_button(ui, (0.75, row(5), 0.23, height), "Close", Event::Close);
+
+        if let Some(message) = &self.message {
+            self.add_text(ui, (0.5, row(6), 0.48, height), message, self.font_size(), HorizontalAlign::Left);
+        }
+    }
+
+    fn build_keyboard(&self, ui: &mut UI) {
+        if let Some((key, _)) = self.settings.texts.get(self.text_index) {
+            self.add_text(ui, (0.02, 0.02, 0.96, 0.08), key, self.small_font_size(), HorizontalAlign::Center);
+        }
+        self.add_text(ui, (0.02, 0.1, 0.96, 0.12), &format!("{}_", self.edited_text), self.font_size(), HorizontalAlign::Center);
+
+        let key_width = 0.96 / 11.;
+        for (row, keys) in KEYBOARD_ROWS.iter().enumerate() {
+            let count = keys.chars().count() as f32;
+            let left = 0.5 - count * key_width / 2.;
+            for (column, key) in keys.chars().enumerate() {
+                let label = if self.shift { key.to_uppercase().collect() } else { key.to_string() };
+                let rect = (left + column as f32 * key_width + 0.003, 0.25 + row as f32 * 0.135, key_width - 0.006, 0.12);
+                self.add_button(ui, rect, &label, Event::Key(key));
+            }
+        }
+
+        let bottom = 0.25 + KEYBOARD_ROWS.len() as f32 * 0.135;
+        self.add_button(ui, (0.02, bottom, 0.15, 0.12), if self.shift { "SHIFT" } else { "Shift" }, Event::Shift);
+        self.add_button(ui, (0.19, bottom, 0.4, 0.12), "Space", Event::Key(' '));
+        self.add_button(ui, (0.61, bottom, 0.12, 0.12), "Del", Event::Erase);
+        self.add_button(ui, (0.75, bottom, 0.11, 0.12), "Cancel", Event::Cancel);
+        self.add_button(ui, (0.87, bottom, 0.11, 0.12), "Done", Event::Done);
+    }
+}
+
+/// `text` cut to `max` characters, with an ellipsis when it was longer
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_many_wrong_pins_lock_the_menu() {
+        let mut attempts = PinAttempts::default();
+        let start = Instant::now();
+        for _ in 1..MAX_PIN_ATTEMPTS {
+            assert!(!attempts.failed(start));
+        }
+        assert!(!attempts.is_locked(start));
+        assert!(attempts.failed(start));
+        assert!(attempts.is_locked(start + PIN_LOCKOUT_TIME - Duration::from_secs(1)));
+        assert!(!attempts.is_locked(start + PIN_LOCKOUT_TIME));
+    }
+
+    #[test]
+    fn every_wrong_pin_after_a_lockout_locks_again() {
+        let mut attempts = PinAttempts::default();
+        let start = Instant::now();
+        for _ in 0..MAX_PIN_ATTEMPTS {
+            attempts.failed(start);
+        }
+        let later = start + PIN_LOCKOUT_TIME;
+        assert!(attempts.failed(later));
+        assert!(attempts.is_locked(later + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn right_pin_resets_the_count() {
+        let mut attempts = PinAttempts::default();
+        let start = Instant::now();
+        for _ in 1..MAX_PIN_ATTEMPTS {
+            attempts.failed(start);
+        }
+        attempts.succeeded();
+        assert!(!attempts.failed(start));
+    }
+}
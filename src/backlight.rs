@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::*;
+
+/// A backlight exposed by the kernel in `/sys/class/backlight`
+pub struct Backlight {
+    path: PathBuf,
+    max_brightness: u32,
+    /// Brightness before it was changed by us
+    original_brightness: u32,
+}
+
+impl Backlight {
+    /// Open the backlight at `path`, or the first backlight in
+    /// `/sys/class/backlight` if no path is given.
+    ///
+    /// Returns `None` if no backlight is present
+    pub fn find(path: Option<&Path>) -> Result<Option<Backlight>> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let Ok(entries) = std::fs::read_dir("/sys/class/backlight") else {
+                    return Ok(None);
+                };
+                let Some(entry) = entries.filter_map(|entry| entry.ok()).next() else {
+                    return Ok(None);
+                };
+                entry.path()
+            }
+        };
+
+        let max_brightness = Self::read_value(&path.join("max_brightness"))?;
+        let original_brightness = Self::read_value(&path.join("brightness"))?;
+
+        info!("Using backlight {:?} (brightness {}/{})", path, original_brightness, max_brightness);
+
+        Ok(Some(Backlight {
+            path,
+            max_brightness,
+            original_brightness,
+        }))
+    }
+
+    fn read_value(path: &Path) -> Result<u32> {
+        Ok(std::fs::read_to_string(path)?.trim().parse()?)
+    }
+
+    /// Set the brightness as a percentage of the maximum brightness
+    pub fn set_percentage(&self, percentage: u32) -> Result<()> {
+        let brightness = self.max_brightness * percentage.min(100) / 100;
+        trace!("Setting backlight brightness to {}", brightness);
+        std::fs::write(self.path.join("brightness"), brightness.to_string())?;
+        Ok(())
+    }
+
+    /// Restore the brightness from before it was changed
+    pub fn restore(&self) -> Result<()> {
+        std::fs::write(self.path.join("brightness"), self.original_brightness.to_string())?;
+        Ok(())
+    }
+}
+
+impl Drop for Backlight {
+    fn drop(&mut self) {
+        _ = self.restore().inspect_err(|err| {
+            warn!("Couldn't restore backlight brightness: {:?}", err);
+        });
+    }
+}
@@ -6,9 +6,10 @@ use std::pin::Pin;
 use std::sync::Arc;
 
 use image::codecs::jpeg::JpegEncoder;
-use image::{EncodableLayout, ImageFormat, Rgba};
+use image::codecs::png::PngEncoder;
+use image::{EncodableLayout, ImageEncoder, ImageError, ImageFormat, Rgba};
 use libcamera::camera::ActiveCamera;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use libcamera::camera_manager::CameraList;
 use libcamera::controls::*;
 use libcamera::framebuffer::AsFrameBuffer;
@@ -20,6 +21,8 @@ use libcamera::utils::Immutable;
 use log::*;
 use ouroboros::self_referencing;
 
+use crate::display::FrameLayout;
+use crate::error::BoothError;
 use crate::utils::image::abgr_to_rgb;
 
 #[self_referencing]
@@ -48,7 +51,6 @@ impl CameraManager {
 // TODO: redirect libcamera logging to log. This requires modifying libcamera bindings
 // Picture settings: https://lit-robotics.github.io/libcamera-rs/libcamera/controls/index.html
 pub struct Camera<'cam> {
-    #[allow(unused)]
     camera: Pin<Box<libcamera::camera::Camera<'cam>>>,
     active_camera: libcamera::camera::ActiveCamera<'cam>,
     #[allow(unused)]
@@ -60,6 +62,10 @@ pub struct Camera<'cam> {
 
 impl<'cam> Camera<'cam> {
     pub fn new(manager: &'cam crate::camera::CameraManager, format: u32, screen_width: u32, screen_height: u32) -> Result<Self> {
+        Self::open(manager, format, screen_width, screen_height).context(BoothError::CameraLost)
+    }
+
+    fn open(manager: &'cam crate::camera::CameraManager, format: u32, screen_width: u32, screen_height: u32) -> Result<Self> {
         let first_camera = Box::pin(manager.cameras()
             .get(0)
             .ok_or_else(|| anyhow!("No cameras found"))?);
@@ -129,6 +135,11 @@ impl<'cam> Camera<'cam> {
         })
     }
 
+    /// The libcamera id of the camera, which includes the sensor model
+    pub fn id(&self) -> String {
+        self.camera.id().to_string()
+    }
+
     pub fn video_stream(&self) -> &VideoStream {
         &self.video_stream
     }
@@ -268,6 +279,24 @@ impl<'cam> Camera<'cam> {
             Arc<image::ImageBuffer<Rgba<u8>, Vec<u8>>>
         >>,
         continue_waiter: Option<std::sync::mpsc::Receiver<()>>,
+    ) -> Result<()> {
+        // Writing errors are tagged already
+        self.capture_still(result_file_writer, image_format, on_capture_sender, on_image_creation_sender, continue_waiter)
+            .map_err(|err| match err.downcast_ref::<BoothError>() {
+                Some(_) => err,
+                None => err.context(BoothError::CaptureFailed),
+            })
+    }
+
+    fn capture_still(
+        &mut self,
+        result_file_writer: &mut BufWriter<File>,
+        image_format: ImageFormat,
+        on_capture_sender: Option<std::sync::mpsc::Sender<()>>,
+        on_image_creation_sender: Option<std::sync::mpsc::Sender<
+            Arc<image::ImageBuffer<Rgba<u8>, Vec<u8>>>
+        >>,
+        continue_waiter: Option<std::sync::mpsc::Receiver<()>>,
     ) -> Result<()> {
         trace!("Capturing picture...");
 
@@ -332,44 +361,28 @@ impl<'cam> Camera<'cam> {
 
         trace!("Displaying image data and writing to file");
 
-        let alpha_supported = match image_format {
-            ImageFormat::Png
-                | ImageFormat::Gif
-                | ImageFormat::WebP
-                | ImageFormat::Tiff
-                | ImageFormat::Tga
-                | ImageFormat::Dds
-                | ImageFormat::Ico
-                | ImageFormat::Hdr
-                | ImageFormat::OpenExr
-                | ImageFormat::Avif
-                | ImageFormat::Bmp => true,
-            _ => false
-        };
-
-        if alpha_supported {
-            unimplemented!("Image format currently not supported");
-            // let img_buffer = Arc::new(img_buffer);
-            // if let Some(sender) = on_image_creation_sender {
-            //     sender.send(img_buffer.clone())?;
-            // }
-
-            // img_buffer.write_to(result_file_writer, image_format)?;
-        } else {
-            let (width, height) = (img_buffer.width(), img_buffer.height());
-            if let Some(sender) = on_image_creation_sender {
-                sender.send(Arc::new(img_buffer))?;
-            }
+        let (width, height) = (img_buffer.width(), img_buffer.height());
+        if let Some(sender) = on_image_creation_sender {
+            sender.send(Arc::new(img_buffer))?;
+        }
 
-            let rgb_buffer = unsafe { abgr_to_rgb(img_data, width as usize, height as usize) };
+        let rgb_buffer = unsafe { abgr_to_rgb(img_data, width as usize, height as usize) };
 
-            if image_format == ImageFormat::Jpeg {
+        let encoded = match image_format {
+            ImageFormat::Jpeg => {
                 let mut encoder = JpegEncoder::new_with_quality(result_file_writer, 85);
-                encoder.encode(rgb_buffer.as_slice(), width, height, image::ExtendedColorType::Rgb8)?;
-            } else {
-                unimplemented!("Image format currently not supported");
-            }
+                encoder.encode(rgb_buffer.as_slice(), width, height, image::ExtendedColorType::Rgb8)
+            },
+            ImageFormat::Png => {
+                let encoder = PngEncoder::new(result_file_writer);
+                encoder.write_image(rgb_buffer.as_slice(), width, height, image::ExtendedColorType::Rgb8)
+            },
+            _ => anyhow::bail!("Image format {:?} is not supported", image_format),
         };
+        encoded.map_err(|err| match err {
+            ImageError::IoError(err) => BoothError::writing(err),
+            err => anyhow::Error::new(err).context(BoothError::WriteFailed),
+        })?;
 
         trace!("Image written with buffered writer with format {:?}", image_format);
 
@@ -397,7 +410,7 @@ impl<'cam> Camera<'cam> {
             return Ok(());
         }
         self.is_streaming = true;
-        self.queue_video_requests()
+        self.queue_video_requests().context(BoothError::CameraLost)
         // self.video_stream.queue_requests(&self.active_camera)
     }
 
@@ -408,7 +421,7 @@ impl<'cam> Camera<'cam> {
         }
         self.is_streaming = false;
         while self.video_stream().requests.len() != self.video_stream().requests_count {
-            let req = self.on_request_receiver().recv()?;
+            let req = self.on_request_receiver().recv().context(BoothError::CameraLost)?;
             self.video_stream_mut().requests.push(req);
         }
         Ok(())
@@ -418,7 +431,7 @@ impl<'cam> Camera<'cam> {
     pub fn resubmit_stream_request(&mut self, mut req: Request) -> Result<()> {
         req.reuse(ReuseFlag::REUSE_BUFFERS);
         if self.is_streaming {
-            self.queue_video_request(req)?;
+            self.queue_video_request(req).context(BoothError::CameraLost)?;
         } else {
             self.video_stream_mut().requests.push(req);
         }
@@ -438,6 +451,7 @@ pub struct VideoStream {
     // video_buffers: Vec<libcamera::framebuffer_allocator::FrameBuffer>,
     // stream_cfg: Immutable<libcamera::stream::StreamConfigurationRef<'stream>>,
     frame_size: u32,
+    frame_layout: FrameLayout,
     pixel_format: PixelFormat,
     requests: Vec<libcamera::request::Request>,
     requests_count: usize,
@@ -498,6 +512,11 @@ impl VideoStream {
             video_stream,
             // video_buffers: buffers,
             frame_size: stream_cfg.get_frame_size(),
+            frame_layout: FrameLayout {
+                width: stream_cfg.get_size().width as usize,
+                height: stream_cfg.get_size().height as usize,
+                stride: stream_cfg.get_stride() as usize,
+            },
             pixel_format: stream_cfg.get_pixel_format(),
             requests,
             requests_count,
@@ -530,6 +549,10 @@ impl VideoStream {
         self.frame_size
     }
 
+    pub fn frame_layout(&self) -> FrameLayout {
+        self.frame_layout
+    }
+
     pub fn get_pixel_format(&self) -> PixelFormat {
         self.pixel_format
     }
@@ -0,0 +1,370 @@
+//! Colors as written in the configuration and drawn to the screen
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+
+/// A color with alpha. In the configuration it is written as `#RRGGBB`,
+/// `#RRGGBBAA`, `rgb(r, g, b)`, `rgba(r, g, b, a)` (alpha from 0 to 1), a CSS
+/// color name like `white`, or a number `0xAARRGGBB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// 0 is fully transparent, 255 opaque
+    pub a: u8,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    /// From `0xAARRGGBB`
+    pub const fn from_argb(argb: u32) -> Color {
+        Color::rgba((argb >> 16) as u8, (argb >> 8) as u8, argb as u8, (argb >> 24) as u8)
+    }
+
+    /// Alpha from 0 (transparent) to 1
+    pub fn alpha(self) -> f32 {
+        self.a as f32 / 255.
+    }
+
+    /// Multiply the alpha by `opacity` (0 - 1)
+    pub fn with_opacity(self, opacity: f32) -> Color {
+        Color { a: (self.a as f32 * opacity.clamp(0., 1.)).round() as u8, ..self }
+    }
+
+    /// Linear interpolation to `to`, alpha included
+    pub fn mix(self, to: Color, t: f32) -> Color {
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color::rgba(mix(self.r, to.r), mix(self.g, to.g), mix(self.b, to.b), mix(self.a, to.a))
+    }
+
+    /// The pixel as stored in an XRGB8888 framebuffer, which is B, G, R, X in
+    /// memory. Alpha is dropped, blend before writing the pixel
+    pub const fn to_xrgb8888(self) -> [u8; 4] {
+        [self.b, self.g, self.r, 0xFF]
+    }
+
+    fn parse_hex(hex: &str) -> Result<Color> {
+        let channel = |i: usize| hex.get(i..i + 2)
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+            .ok_or_else(|| anyhow!("invalid hex color #{}", hex));
+        match hex.len() {
+            6 => Ok(Color::rgb(channel(0)?, channel(2)?, channel(4)?)),
+            8 => Ok(Color::rgba(channel(0)?, channel(2)?, channel(4)?, channel(6)?)),
+            _ => bail!("hex colors are #RRGGBB or #RRGGBBAA, got #{}", hex),
+        }
+    }
+
+    /// The arguments of `rgb(...)` or `rgba(...)`
+    fn parse_function(args: &str, with_alpha: bool) -> Result<Color> {
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        let expected = if with_alpha { 4 } else { 3 };
+        if args.len() != expected {
+            bail!("expected {} values, got {}", expected, args.len());
+        }
+        let channel = |arg: &str| arg.parse::<u8>().map_err(|_| anyhow!("{} is not a number from 0 to 255", arg));
+        let alpha = match args.get(3) {
+            Some(arg) => match arg.parse::<f32>() {
+                Ok(alpha) if (0. ..=1.).contains(&alpha) => (alpha * 255.).round() as u8,
+                _ => bail!("alpha {} is not a number from 0 to 1", arg),
+            },
+            None => 255,
+        };
+        Ok(Color::rgba(channel(args[0])?, channel(args[1])?, channel(args[2])?, alpha))
+    }
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        if let Some(hex) = s.strip_prefix('#') {
+            return Color::parse_hex(hex);
+        }
+        if let Some(hex) = s.strip_prefix("0x") {
+            return u32::from_str_radix(hex, 16).map(Color::from_argb).map_err(|_| anyhow!("invalid color 0x{}", hex));
+        }
+        if let Some(args) = s.strip_prefix("rgba(").and_then(|rest| rest.strip_suffix(')')) {
+            return Color::parse_function(args, true);
+        }
+        if let Some(args) = s.strip_prefix("rgb(").and_then(|rest| rest.strip_suffix(')')) {
+            return Color::parse_function(args, false);
+        }
+        NAMED_COLORS.iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, color)| *color)
+            .ok_or_else(|| anyhow!("unknown color {:?}", s))
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02X}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ColorVisitor;
+
+        impl serde::de::Visitor<'_> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a color like \"#RRGGBB\", \"#RRGGBBAA\", \"rgb(r, g, b)\", a color name or 0xAARRGGBB")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Color, E> {
+                u32::try_from(value).map(Color::from_argb).map_err(|_| E::custom(format!("color 0x{:X} is larger than 0xFFFFFFFF", value)))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Color, E> {
+                u64::try_from(value).map_err(|_| E::custom("colors can't be negative")).and_then(|value| self.visit_u64(value))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Color, E> {
+                value.parse().map_err(|err| E::custom(format!("{:#}", err)))
+            }
+        }
+
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The CSS named colors
+const NAMED_COLORS: [(&str, Color); 149] = [
+    ("aliceblue", Color::rgb(240, 248, 255)),
+    ("antiquewhite", Color::rgb(250, 235, 215)),
+    ("aqua", Color::rgb(0, 255, 255)),
+    ("aquamarine", Color::rgb(127, 255, 212)),
+    ("azure", Color::rgb(240, 255, 255)),
+    ("beige", Color::rgb(245, 245, 220)),
+    ("bisque", Color::rgb(255, 228, 196)),
+    ("black", Color::rgb(0, 0, 0)),
+    ("blanchedalmond", Color::rgb(255, 235, 205)),
+    ("blue", Color::rgb(0, 0, 255)),
+    ("blueviolet", Color::rgb(138, 43, 226)),
+    ("brown", Color::rgb(165, 42, 42)),
+    ("burlywood", Color::rgb(222, 184, 135)),
+    ("cadetblue", Color::rgb(95, 158, 160)),
+    ("chartreuse", Color::rgb(127, 255, 0)),
+    ("chocolate", Color::rgb(210, 105, 30)),
+    ("coral", Color::rgb(255, 127, 80)),
+    ("cornflowerblue", Color::rgb(100, 149, 237)),
+    ("cornsilk", Color::rgb(255, 248, 220)),
+    ("crimson", Color::rgb(220, 20, 60)),
+    ("cyan", Color::rgb(0, 255, 255)),
+    ("darkblue", Color::rgb(0, 0, 139)),
+    ("darkcyan", Color::rgb(0, 139, 139)),
+    ("darkgoldenrod", Color::rgb(184, 134, 11)),
+    ("darkgray", Color::rgb(169, 169, 169)),
+    ("darkgreen", Color::rgb(0, 100, 0)),
+    ("darkgrey", Color::rgb(169, 169, 169)),
+    ("darkkhaki", Color::rgb(189, 183, 107)),
+    ("darkmagenta", Color::rgb(139, 0, 139)),
+    ("darkolivegreen", Color::rgb(85, 107, 47)),
+    ("darkorange", Color::rgb(255, 140, 0)),
+    ("darkorchid", Color::rgb(153, 50, 204)),
+    ("darkred", Color::rgb(139, 0, 0)),
+    ("darksalmon", Color::rgb(233, 150, 122)),
+    ("darkseagreen", Color::rgb(143, 188, 143)),
+    ("darkslateblue", Color::rgb(72, 61, 139)),
+    ("darkslategray", Color::rgb(47, 79, 79)),
+    ("darkslategrey", Color::rgb(47, 79, 79)),
+    ("darkturquoise", Color::rgb(0, 206, 209)),
+    ("darkviolet", Color::rgb(148, 0, 211)),
+    ("deeppink", Color::rgb(255, 20, 147)),
+    ("deepskyblue", Color::rgb(0, 191, 255)),
+    ("dimgray", Color::rgb(105, 105, 105)),
+    ("dimgrey", Color::rgb(105, 105, 105)),
+    ("dodgerblue", Color::rgb(30, 144, 255)),
+    ("firebrick", Color::rgb(178, 34, 34)),
+    ("floralwhite", Color::rgb(255, 250, 240)),
+    ("forestgreen", Color::rgb(34, 139, 34)),
+    ("fuchsia", Color::rgb(255, 0, 255)),
+    ("gainsboro", Color::rgb(220, 220, 220)),
+    ("ghostwhite", Color::rgb(248, 248, 255)),
+    ("gold", Color::rgb(255, 215, 0)),
+    ("goldenrod", Color::rgb(218, 165, 32)),
+    ("gray", Color::rgb(128, 128, 128)),
+    ("green", Color::rgb(0, 128, 0)),
+    ("greenyellow", Color::rgb(173, 255, 47)),
+    ("grey", Color::rgb(128, 128, 128)),
+    ("honeydew", Color::rgb(240, 255, 240)),
+    ("hotpink", Color::rgb(255, 105, 180)),
+    ("indianred", Color::rgb(205, 92, 92)),
+    ("indigo", Color::rgb(75, 0, 130)),
+    ("ivory", Color::rgb(255, 255, 240)),
+    ("khaki", Color::rgb(240, 230, 140)),
+    ("lavender", Color::rgb(230, 230, 250)),
+    ("lavenderblush", Color::rgb(255, 240, 245)),
+    ("lawngreen", Color::rgb(124, 252, 0)),
+    ("lemonchiffon", Color::rgb(255, 250, 205)),
+    ("lightblue", Color::rgb(173, 216, 230)),
+    ("lightcoral", Color::rgb(240, 128, 128)),
+    ("lightcyan", Color::rgb(224, 255, 255)),
+    ("lightgoldenrodyellow", Color::rgb(250, 250, 210)),
+    ("lightgray", Color::rgb(211, 211, 211)),
+    ("lightgreen", Color::rgb(144, 238, 144)),
+    ("lightgrey", Color::rgb(211, 211, 211)),
+    ("lightpink", Color::rgb(255, 182, 193)),
+    ("lightsalmon", Color::rgb(255, 160, 122)),
+    ("lightseagreen", Color::rgb(32, 178, 170)),
+    ("lightskyblue", Color::rgb(135, 206, 250)),
+    ("lightslategray", Color::rgb(119, 136, 153)),
+    ("lightslategrey", Color::rgb(119, 136, 153)),
+    ("lightsteelblue", Color::rgb(176, 196, 222)),
+    ("lightyellow", Color::rgb(255, 255, 224)),
+    ("lime", Color::rgb(0, 255, 0)),
+    ("limegreen", Color::rgb(50, 205, 50)),
+    ("linen", Color::rgb(250, 240, 230)),
+    ("magenta", Color::rgb(255, 0, 255)),
+    ("maroon", Color::rgb(128, 0, 0)),
+    ("mediumaquamarine", Color::rgb(102, 205, 170)),
+    ("mediumblue", Color::rgb(0, 0, 205)),
+    ("mediumorchid", Color::rgb(186, 85, 211)),
+    ("mediumpurple", Color::rgb(147, 112, 219)),
+    ("mediumseagreen", Color::rgb(60, 179, 113)),
+    ("mediumslateblue", Color::rgb(123, 104, 238)),
+    ("mediumspringgreen", Color::rgb(0, 250, 154)),
+    ("mediumturquoise", Color::rgb(72, 209, 204)),
+    ("mediumvioletred", Color::rgb(199, 21, 133)),
+    ("midnightblue", Color::rgb(25, 25, 112)),
+    ("mintcream", Color::rgb(245, 255, 250)),
+    ("mistyrose", Color::rgb(255, 228, 225)),
+    ("moccasin", Color::rgb(255, 228, 181)),
+    ("navajowhite", Color::rgb(255, 222, 173)),
+    ("navy", Color::rgb(0, 0, 128)),
+    ("oldlace", Color::rgb(253, 245, 230)),
+    ("olive", Color::rgb(128, 128, 0)),
+    ("olivedrab", Color::rgb(107, 142, 35)),
+    ("orange", Color::rgb(255, 165, 0)),
+    ("orangered", Color::rgb(255, 69, 0)),
+    ("orchid", Color::rgb(218, 112, 214)),
+    ("palegoldenrod", Color::rgb(238, 232, 170)),
+    ("palegreen", Color::rgb(152, 251, 152)),
+    ("paleturquoise", Color::rgb(175, 238, 238)),
+    ("palevioletred", Color::rgb(219, 112, 147)),
+    ("papayawhip", Color::rgb(255, 239, 213)),
+    ("peachpuff", Color::rgb(255, 218, 185)),
+    ("peru", Color::rgb(205, 133, 63)),
+    ("pink", Color::rgb(255, 192, 203)),
+    ("plum", Color::rgb(221, 160, 221)),
+    ("powderblue", Color::rgb(176, 224, 230)),
+    ("purple", Color::rgb(128, 0, 128)),
+    ("rebeccapurple", Color::rgb(102, 51, 153)),
+    ("red", Color::rgb(255, 0, 0)),
+    ("rosybrown", Color::rgb(188, 143, 143)),
+    ("royalblue", Color::rgb(65, 105, 225)),
+    ("saddlebrown", Color::rgb(139, 69, 19)),
+    ("salmon", Color::rgb(250, 128, 114)),
+    ("sandybrown", Color::rgb(244, 164, 96)),
+    ("seagreen", Color::rgb(46, 139, 87)),
+    ("seashell", Color::rgb(255, 245, 238)),
+    ("sienna", Color::rgb(160, 82, 45)),
+    ("silver", Color::rgb(192, 192, 192)),
+    ("skyblue", Color::rgb(135, 206, 235)),
+    ("slateblue", Color::rgb(106, 90, 205)),
+    ("slategray", Color::rgb(112, 128, 144)),
+    ("slategrey", Color::rgb(112, 128, 144)),
+    ("snow", Color::rgb(255, 250, 250)),
+    ("springgreen", Color::rgb(0, 255, 127)),
+    ("steelblue", Color::rgb(70, 130, 180)),
+    ("tan", Color::rgb(210, 180, 140)),
+    ("teal", Color::rgb(0, 128, 128)),
+    ("thistle", Color::rgb(216, 191, 216)),
+    ("tomato", Color::rgb(255, 99, 71)),
+    ("transparent", Color::TRANSPARENT),
+    ("turquoise", Color::rgb(64, 224, 208)),
+    ("violet", Color::rgb(238, 130, 238)),
+    ("wheat", Color::rgb(245, 222, 179)),
+    ("white", Color::rgb(255, 255, 255)),
+    ("whitesmoke", Color::rgb(245, 245, 245)),
+    ("yellow", Color::rgb(255, 255, 0)),
+    ("yellowgreen", Color::rgb(154, 205, 50)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Color {
+        s.parse().unwrap_or_else(|err| panic!("{:?}: {:#}", s, err))
+    }
+
+    #[test]
+    fn parses_hex() {
+        assert_eq!(parse("#FF8000"), Color::rgb(255, 128, 0));
+        assert_eq!(parse("#ff800040"), Color::rgba(255, 128, 0, 64));
+        assert_eq!(parse("  #Ff8000 "), Color::rgb(255, 128, 0));
+        assert!("#FF80".parse::<Color>().is_err());
+        assert!("#GG8000".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn parses_functions() {
+        assert_eq!(parse("rgb(255, 128, 0)"), Color::rgb(255, 128, 0));
+        assert_eq!(parse("RGBA(255,128,0,0.5)"), Color::rgba(255, 128, 0, 128));
+        assert_eq!(parse("rgba(1, 2, 3, 0)"), Color::rgba(1, 2, 3, 0));
+        assert!("rgb(256, 0, 0)".parse::<Color>().is_err());
+        assert!("rgb(1, 2, 3, 1)".parse::<Color>().is_err());
+        assert!("rgba(1, 2, 3, 1.5)".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(parse("white"), Color::WHITE);
+        assert_eq!(parse("RebeccaPurple"), Color::rgb(102, 51, 153));
+        assert_eq!(parse("transparent"), Color::TRANSPARENT);
+        assert!("blurple".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn numbers_are_argb() {
+        let color = Color::rgba(0x11, 0x22, 0x33, 0x80);
+        assert_eq!(Color::from_argb(0x80112233), color);
+        assert_eq!(parse("0x80112233"), color);
+        assert_eq!(serde_yaml::from_str::<Color>("0x80112233").unwrap(), color);
+        assert_eq!(serde_yaml::from_str::<Color>(&0x80112233u32.to_string()).unwrap(), color);
+        assert!(serde_yaml::from_str::<Color>("-1").is_err());
+        assert!(serde_yaml::from_str::<Color>("0x100000000").is_err());
+    }
+
+    #[test]
+    fn framebuffer_pixels_are_bgrx() {
+        assert_eq!(Color::from_argb(0x80112233).to_xrgb8888(), [0x33, 0x22, 0x11, 0xFF]);
+        assert_eq!(parse("#112233").to_xrgb8888(), [0x33, 0x22, 0x11, 0xFF]);
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for color in [Color::rgb(255, 128, 0), Color::rgba(1, 2, 3, 4), Color::TRANSPARENT] {
+            assert_eq!(parse(&color.to_string()), color);
+        }
+        assert_eq!(Color::rgb(255, 128, 0).to_string(), "#FF8000");
+    }
+}
@@ -1,22 +1,668 @@
-#[derive(serde::Deserialize, Debug)]
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use inotify::{Inotify, WatchMask};
+use log::*;
+
+use crate::color::Color;
+use crate::display::{DisplayBackendKind, Rotation};
+use crate::error::BoothError;
+use crate::input::{keys, CalibrationMatrix, InputAction};
+use crate::ui::{Align, BackgroundImage, LayoutElement, LayoutElementKind, Length, TextOutline, TextShadow};
+
+/// The configuration file, set by the `PH_CONFIG` environment variable
+pub fn config_path() -> PathBuf {
+    std::env::var_os("PH_CONFIG").unwrap_or("config.yaml".into()).into()
+}
+
+/// Settings changed while running (admin menu, touch calibration), next to
+/// the configuration file at `config`: `config.yaml` has `config.state.yaml`.
+/// They override the configuration file, which is never written
+pub fn state_path(config: &Path) -> PathBuf {
+    config.with_extension("state.yaml")
+}
+
+/// Something wrong in the configuration file. The booth keeps running, with
+/// the setting ignored or replaced by its default
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    /// Path of the setting, e.g. `layouts.review.2.fontSize`
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigProblem {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+/// Read and validate the configuration file at `path`. Problems are returned
+/// instead of failing, the default configuration is used when the file can't
+/// be read
+pub fn load(path: &Path) -> (Config, Vec<ConfigProblem>) {
+    let mut problems = Vec::new();
+    let mut config = match read(path, &mut problems) {
+        Ok(config) => config,
+        Err(err) => {
+            problems.push(ConfigProblem::new(path.to_string_lossy(), format!("{:#}, using the default configuration", err)));
+            Config::default()
+        },
+    };
+    problems.extend(config.validate());
+    (config, problems)
+}
+
+fn read(path: &Path, problems: &mut Vec<ConfigProblem>) -> Result<Config> {
+    let state_path = state_path(path);
+    let has_state = std::fs::exists(&state_path)?;
+    if !std::fs::exists(path)? && !has_state {
+        info!("Configuration file not found, using default");
+        return Ok(Config::default());
+    }
+    let text = if std::fs::exists(path)? { std::fs::read_to_string(path)? } else { String::new() };
+    let mut document = parse_document(&text)?;
+    if has_state {
+        let state = read_document(&state_path).with_context(|| format!("Couldn't read {:?}", state_path))?;
+        for keys in leaf_keys(&state) {
+            if lookup(&document, &keys).is_some_and(|value| Some(value) != lookup(&state, &keys)) {
+                warn!("{} in the configuration file is overridden by {:?}", key_name(&keys), state_path);
+            }
+        }
+        merge(&mut document, state);
+    }
+    let mut config = deserialize_config(document, &text, problems)?;
+    config.resolve_paths(path.parent().unwrap_or(Path::new("")));
+    Ok(config)
+}
+
+/// Read `document` as configuration. Settings of the wrong type are left out
+/// with a problem, so one mistake doesn't reset the whole file. `text` is the
+/// configuration file, to tell the line of such settings
+fn deserialize_config(mut document: serde_yaml::Value, text: &str, problems: &mut Vec<ConfigProblem>) -> Result<Config> {
+    let Some(mapping) = document.as_mapping_mut() else {
+        anyhow::bail!("expected settings like `countdown: 3` at the top level");
+    };
+    if serde_yaml::from_value::<Config>(serde_yaml::Value::Mapping(mapping.clone())).is_err() {
+        let invalid: Vec<_> = mapping.iter().filter_map(|(key, value)| {
+            let setting = serde_yaml::Mapping::from_iter([(key.clone(), value.clone())]);
+            serde_yaml::from_value::<Config>(serde_yaml::Value::Mapping(setting)).err().map(|err| (key.clone(), err))
+        }).collect();
+        for (key, err) in invalid {
+            mapping.remove(&key);
+            let name = key_name(std::slice::from_ref(&key));
+            let line = text.lines().position(|line| line.starts_with(&format!("{}:", name)) || line.starts_with(&format!("\"{}\":", name)));
+            let location = line.map(|line| format!(" (line {})", line + 1)).unwrap_or_default();
+            problems.push(ConfigProblem::new(name, format!("{}{}, using the default", err, location)));
+        }
+    }
+    let config = serde_ignored::deserialize(document, |key| {
+        problems.push(ConfigProblem::new(ignored_key(key), "unknown setting, ignored"));
+    })?;
+    Ok(config)
+}
+
+/// The YAML document at `path`, an empty mapping when the file doesn't exist
+/// or is empty
+fn read_document(path: &Path) -> Result<serde_yaml::Value> {
+    if !std::fs::exists(path)? {
+        return Ok(serde_yaml::Value::Mapping(Default::default()));
+    }
+    parse_document(&std::fs::read_to_string(path)?)
+}
+
+/// The YAML document in `text`, an empty mapping when it is empty
+fn parse_document(text: &str) -> Result<serde_yaml::Value> {
+    Ok(match serde_yaml::from_str(text)? {
+        serde_yaml::Value::Null => serde_yaml::Value::Mapping(Default::default()),
+        document => document,
+    })
+}
+
+/// Paths of the values in `document` that aren't mappings, e.g.
+/// `[texts, en, keepText]`
+fn leaf_keys(document: &serde_yaml::Value) -> Vec<Vec<serde_yaml::Value>> {
+    let Some(mapping) = document.as_mapping() else {
+        return Vec::new();
+    };
+    mapping.iter().flat_map(|(key, value)| match value {
+        serde_yaml::Value::Mapping(_) => leaf_keys(value).into_iter().map(|mut keys| {
+            keys.insert(0, key.clone());
+            keys
+        }).collect(),
+        _ => vec![vec![key.clone()]],
+    }).collect()
+}
+
+/// The value at `keys` in nested mappings
+fn lookup<'a>(document: &'a serde_yaml::Value, keys: &[serde_yaml::Value]) -> Option<&'a serde_yaml::Value> {
+    keys.iter().try_fold(document, |value, key| value.as_mapping()?.get(key))
+}
+
+/// `keys` as written in problems, e.g. `texts.en.keepText`
+fn key_name(keys: &[serde_yaml::Value]) -> String {
+    let names: Vec<String> = keys.iter().map(|key| match key {
+        serde_yaml::Value::String(key) => key.clone(),
+        key => serde_yaml::to_string(key).unwrap_or_default().trim().to_string(),
+    }).collect();
+    names.join(".")
+}
+
+/// Put the values of `overrides` in `document`. Mappings in both are merged,
+/// other values are replaced
+fn merge(document: &mut serde_yaml::Value, overrides: serde_yaml::Value) {
+    match (document, overrides) {
+        (serde_yaml::Value::Mapping(document), serde_yaml::Value::Mapping(overrides)) => {
+            for (key, value) in overrides {
+                match document.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => _ = document.insert(key, value),
+                }
+            }
+        },
+        (document, overrides) => *document = overrides,
+    }
+}
+
+/// The path of a setting as written in the file. Options show up as `?`
+fn ignored_key(path: serde_ignored::Path) -> String {
+    path.to_string().replace(".?", "").replace("?.", "")
+}
+
+/// Notices when the configuration file is written. The directory is watched
+/// rather than the file, as editors often save by replacing the file
+pub struct ConfigWatcher {
+    inotify: Inotify,
+    path: PathBuf,
+    file_name: OsString,
+    buffer: [u8; 4096],
+    /// The file as last read, to tell which settings an edit changed. `None`
+    /// when it couldn't be parsed
+    document: Option<serde_yaml::Value>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Result<Self> {
+        let file_name = path.file_name().with_context(|| format!("{:?} is not a file", path))?.to_owned();
+        let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let inotify = Inotify::init()?;
+        inotify.watches().add(directory, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)?;
+        Ok(ConfigWatcher {
+            inotify,
+            path: path.to_owned(),
+            file_name,
+            buffer: [0; 4096],
+            document: read_document(path).ok(),
+        })
+    }
+
+    /// Read the configuration file again while the booth is running. Unlike
+    /// `load`, a missing file or one that can't be parsed fails the reload, so
+    /// a half finished edit doesn't replace a working configuration. Problems
+    /// fixed by validation are returned like `load` does.
+    ///
+    /// Settings saved in the state file are dropped when the edit changed
+    /// them in the configuration file, so the edit takes effect
+    pub fn reload(&mut self) -> Result<(Config, Vec<ConfigProblem>)> {
+        if !std::fs::exists(&self.path)? {
+            anyhow::bail!("{:?} not found", self.path);
+        }
+        let document = read_document(&self.path)?;
+        if let Some(previous) = self.document.replace(document.clone()) {
+            drop_changed_state(&self.path, &previous, &document)?;
+        }
+        let mut problems = Vec::new();
+        let mut config = read(&self.path, &mut problems)?;
+        problems.extend(config.validate());
+        Ok((config, problems))
+    }
+
+    /// Whether the file was written since the last call. Doesn't block
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.inotify.read_events(&mut self.buffer) {
+                Ok(events) => {
+                    let mut any = false;
+                    for event in events {
+                        any = true;
+                        changed |= event.name == Some(self.file_name.as_os_str());
+                    }
+                    if !any {
+                        return changed;
+                    }
+                },
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return changed,
+                Err(err) => {
+                    warn!("Couldn't watch the configuration file: {}", err);
+                    return changed;
+                },
+            }
+        }
+    }
+}
+
+/// Remove the settings from the state file of the configuration file at
+/// `path` that differ between its `previous` and `current` contents
+fn drop_changed_state(path: &Path, previous: &serde_yaml::Value, current: &serde_yaml::Value) -> Result<()> {
+    let state_path = state_path(path);
+    if !std::fs::exists(&state_path)? {
+        return Ok(());
+    }
+    let mut state = read_document(&state_path)?;
+    let changed: Vec<_> = leaf_keys(&state).into_iter()
+        .filter(|keys| lookup(previous, keys) != lookup(current, keys))
+        .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    for keys in &changed {
+        info!("{} changed in the configuration file, dropping the value in {:?}", key_name(keys), state_path);
+        let Some((key, parents)) = keys.split_last() else {
+            continue;
+        };
+        if let Some(serde_yaml::Value::Mapping(mapping)) = parents.iter().try_fold(&mut state, |value, parent| value.as_mapping_mut()?.get_mut(parent)) {
+            mapping.remove(key);
+        }
+    }
+    write_atomically(&state_path, serde_yaml::to_string(&state)?.as_bytes())
+}
+
+/// Set `key` to `value` in the state file, so it overrides the
+/// configuration file from now on. The file is created if it doesn't exist
+pub fn persist_value(key: &str, value: impl serde::Serialize) -> Result<()> {
+    persist_nested_value(&[key], value)
+}
+
+/// Like `persist_value`, for a key nested in mappings, e.g.
+/// `["texts", "nl", "keepText"]`. Missing mappings are created
+pub fn persist_nested_value(keys: &[&str], value: impl serde::Serialize) -> Result<()> {
+    let Some((key, parents)) = keys.split_last() else {
+        anyhow::bail!("No key to persist");
+    };
+    let path = state_path(&config_path());
+    let mut document = read_document(&path)?;
+
+    let mut mapping = &mut document;
+    for parent in parents {
+        let serde_yaml::Value::Mapping(parent_mapping) = mapping else {
+            anyhow::bail!("{:?} in state file {:?} is not a mapping", parents, path);
+        };
+        mapping = parent_mapping.entry((*parent).into()).or_insert_with(|| serde_yaml::Value::Mapping(Default::default()));
+    }
+    let serde_yaml::Value::Mapping(mapping) = mapping else {
+        anyhow::bail!("{:?} in state file {:?} is not a mapping", parents, path);
+    };
+    mapping.insert((*key).into(), serde_yaml::to_value(value)?);
+
+    write_atomically(&path, serde_yaml::to_string(&document)?.as_bytes())
+}
+
+/// Write `contents` to a temporary file next to `path` and move it in place,
+/// so a power cut never leaves a half written file
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut temporary_name = OsString::from(".");
+    temporary_name.push(path.file_name().with_context(|| format!("{:?} is not a file", path))?);
+    temporary_name.push(".tmp");
+    let temporary_path = path.with_file_name(temporary_name);
+
+    let mut file = std::fs::File::create(&temporary_path)?;
+    std::io::Write::write_all(&mut file, contents)?;
+    file.sync_all()?;
+    std::fs::rename(&temporary_path, path)?;
+    Ok(())
+}
+
+/// File format of the photos
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PhotoFormat {
+    #[default]
+    Jpeg,
+    /// Lossless, but much larger files
+    Png,
+}
+
+impl PhotoFormat {
+    pub fn image_format(&self) -> image::ImageFormat {
+        match self {
+            PhotoFormat::Jpeg => image::ImageFormat::Jpeg,
+            PhotoFormat::Png => image::ImageFormat::Png,
+        }
+    }
+}
+
+/// What happens to the screen when the booth is idle
+#[derive(serde::Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IdleAction {
+    /// Lower the backlight brightness to `idleBrightness`
+    Dim,
+    /// Turn the panel off
+    #[default]
+    Blank,
+}
+
+/// The elements shown on each screen. Text can contain the placeholders
+/// listed for the screen, e.g. `{countdown}`
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Layouts {
+    /// Waiting for a picture to be taken. Placeholders: `{takePictureText}`,
+    /// `{galleryText}`, `{languageName}`. Elements with the `nextLanguage`
+    /// action are left out when there is only one language
+    pub take_picture_prompt: Vec<LayoutElement>,
+    /// The countdown over the camera preview. Placeholders: `{countdown}`.
+    /// Text size defaults to `countdownTextSize`
+    pub taking_picture: Vec<LayoutElement>,
+    /// Shown after the picture was taken. Placeholders: `{doneSentence}`
+    pub take_picture: Vec<LayoutElement>,
+    /// Placeholders: `{errorMessage}` and `{errorCode}` (e.g. `E101`, see
+    /// `BoothError::code`)
+    pub error: Vec<LayoutElement>,
+    /// Shown over the recent photos when the booth is idle. Placeholders:
+    /// `{attractText}`
+    pub attract: Vec<LayoutElement>,
+    /// Shown over the photo that was just taken. Placeholders: `{keepText}`,
+    /// `{retakeText}` and `{deleteText}`. Countdown rings empty until the
+    /// photo is kept automatically
+    pub review: Vec<LayoutElement>,
+    /// Drawn below the thumbnails or over the photo being viewed.
+    /// Placeholders: `{position}` (the page or photo, e.g. `2 / 5`) and
+    /// `{backText}`
+    pub gallery: Vec<LayoutElement>,
+}
+
+impl Layouts {
+    /// The layouts by key in the configuration file
+    fn by_key_mut(&mut self) -> [(&'static str, &mut Vec<LayoutElement>); 7] {
+        [
+            ("takePicturePrompt", &mut self.take_picture_prompt),
+            ("takingPicture", &mut self.taking_picture),
+            ("takePicture", &mut self.take_picture),
+            ("error", &mut self.error),
+            ("attract", &mut self.attract),
+            ("review", &mut self.review),
+            ("gallery", &mut self.gallery),
+        ]
+    }
+}
+
+impl Default for Layouts {
+    fn default() -> Self {
+        Layouts {
+            take_picture_prompt: vec![
+                LayoutElement::full_screen_text("{takePictureText}").with_action(InputAction::StartCapture),
+                LayoutElement::button("{galleryText}").with_bounds(78., 86., 20., 12.).with_font_size(50.).with_action(InputAction::OpenGallery),
+                LayoutElement::button("{languageName}").with_bounds(2., 86., 20., 12.).with_font_size(50.).with_action(InputAction::NextLanguage),
+            ],
+            taking_picture: vec![
+                LayoutElement::full_screen_countdown_ring(),
+                LayoutElement::full_screen_text("{countdown}"),
+            ],
+            take_picture: vec![LayoutElement::full_screen_text("{doneSentence}")],
+            error: vec![
+                LayoutElement::full_screen_text("{errorMessage}"),
+                LayoutElement {
+                    align: Align::Right,
+                    ..LayoutElement::full_screen_text("{errorCode}")
+                }.with_bounds(0., 88., 98., 10.).with_font_size(30.),
+            ],
+            attract: vec![
+                LayoutElement {
+                    kind: LayoutElementKind::Panel { fill: Color::from_argb(0xA0000000), radius: 0. },
+                    ..LayoutElement::full_screen_text("")
+                }.with_bounds(0., 80., 100., 20.),
+                LayoutElement::full_screen_text("{attractText}").with_bounds(0., 80., 100., 20.),
+            ],
+            gallery: vec![
+                LayoutElement {
+                    kind: LayoutElementKind::Panel { fill: Color::from_argb(0xA0000000), radius: 0. },
+                    ..LayoutElement::full_screen_text("")
+                }.with_bounds(0., 85., 100., 15.),
+                LayoutElement::button("{backText}").with_bounds(2., 87., 20., 11.).with_font_size(50.).with_action(InputAction::Back),
+                LayoutElement::button("<").with_bounds(30., 87., 12., 11.).with_font_size(50.).with_action(InputAction::Previous),
+                LayoutElement::full_screen_text("{position}").with_bounds(42., 87., 16., 11.).with_font_size(50.),
+                LayoutElement::button(">").with_bounds(58., 87., 12., 11.).with_font_size(50.).with_action(InputAction::Next),
+            ],
+            review: vec![
+                LayoutElement {
+                    kind: LayoutElementKind::Panel { fill: Color::from_argb(0xA0000000), radius: 0. },
+                    ..LayoutElement::full_screen_text("")
+                }.with_bounds(0., 82., 100., 18.),
+                LayoutElement::button("{retakeText}").with_bounds(5., 84., 25., 14.).with_font_size(50.).with_action(InputAction::Retake),
+                LayoutElement::button("{deleteText}").with_bounds(37.5, 84., 25., 14.).with_font_size(50.).with_action(InputAction::Delete),
+                LayoutElement::button("{keepText}").with_bounds(70., 84., 25., 14.).with_font_size(50.).with_action(InputAction::Keep),
+                LayoutElement {
+                    kind: LayoutElementKind::CountdownRing { color: Color::from_argb(0xC0FFFFFF), thickness: 8. },
+                    ..LayoutElement::full_screen_text("")
+                }.with_bounds(90., 2., 8., 8.),
+            ],
+        }
+    }
+}
+
+/// Images behind the screens. The camera preview fills the countdown screen,
+/// so it has no background
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Backgrounds {
+    pub take_picture_prompt: Option<BackgroundImage>,
+    pub take_picture: Option<BackgroundImage>,
+    pub error: Option<BackgroundImage>,
+}
+
+/// A named set of colors and backgrounds, replacing the ones set at the top
+/// level of the configuration when selected with `theme`
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Theme {
+    pub bg_color: Option<Color>,
+    pub error_bg_color: Option<Color>,
+    pub text_color: Option<Color>,
+    pub backgrounds: Option<Backgrounds>,
+}
+
+/// Everything shown to guests, in one language
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Texts {
+    /// Shown on the language button of the prompt screen
+    pub language_name: String,
+    pub take_picture_text: String,
+    /// One of these is shown after a picture was taken
+    pub done_sentences: Vec<String>,
+    pub attract_text: String,
+    pub keep_text: String,
+    pub retake_text: String,
+    pub delete_text: String,
+    pub gallery_text: String,
+    /// Shown instead of the position when no photos were taken yet
+    pub gallery_empty_text: String,
+    pub back_text: String,
+    pub calibration_text: String,
+    pub unknown_error_message: String,
+    pub error_no_usb_device: String,
+    pub error_storage_full: String,
+    pub error_write_failed: String,
+    pub error_empty_file_written: String,
+    pub error_camera_lost: String,
+    pub error_capture_failed: String,
+    pub error_display_failed: String,
+    /// Shown while no touch screen, bound key or mouse is connected
+    pub error_no_touch_device: String,
+    pub error_calibration_failed: String,
+}
+
+impl Default for Texts {
+    fn default() -> Self {
+        Texts {
+            language_name: "English".to_string(),
+            take_picture_text: "Touch to take a picture".to_string(),
+            done_sentences: [
+                "All done!",
+                "You look great!",
+                "Come closer again",
+                "Looking good 😎",
+                "Curious to see the result?"
+            ].map(|s| s.into()).to_vec(),
+            attract_text: "Touch to take your own picture!".to_string(),
+            keep_text: "Keep".to_string(),
+            retake_text: "Retake".to_string(),
+            delete_text: "Delete".to_string(),
+            gallery_text: "Gallery".to_string(),
+            gallery_empty_text: "No photos yet".to_string(),
+            back_text: "Back".to_string(),
+            calibration_text: "Touch the center of the cross".to_string(),
+            unknown_error_message: "Something went wrong, please try again".to_string(),
+            error_no_usb_device: "No USB device connected".to_string(),
+            error_storage_full: "The USB device is full".to_string(),
+            error_write_failed: "The photo couldn't be saved".to_string(),
+            error_empty_file_written: "The photo couldn't be saved".to_string(),
+            error_camera_lost: "The camera is not responding".to_string(),
+            error_capture_failed: "The photo couldn't be taken, please try again".to_string(),
+            error_display_failed: "The screen is not working".to_string(),
+            error_no_touch_device: "The touch screen is not responding".to_string(),
+            error_calibration_failed: "Calibration failed, please try again".to_string(),
+        }
+    }
+}
+
+impl Texts {
+    /// Texts that can be changed in the admin menu, by key in the
+    /// configuration file
+    pub const EDITABLE: [&str; 7] = [
+        "takePictureText",
+        "attractText",
+        "galleryText",
+        "keepText",
+        "retakeText",
+        "deleteText",
+        "errorNoUsbDevice",
+    ];
+
+    /// The text called `key` in the configuration file, see `EDITABLE`
+    pub fn text_mut(&mut self, key: &str) -> Option<&mut String> {
+        match key {
+            "takePictureText" => Some(&mut self.take_picture_text),
+            "attractText" => Some(&mut self.attract_text),
+            "galleryText" => Some(&mut self.gallery_text),
+            "keepText" => Some(&mut self.keep_text),
+            "retakeText" => Some(&mut self.retake_text),
+            "deleteText" => Some(&mut self.delete_text),
+            "errorNoUsbDevice" => Some(&mut self.error_no_usb_device),
+            _ => None,
+        }
+    }
+
+    /// The message shown to guests for `error`
+    pub fn error_message(&self, error: BoothError) -> &str {
+        match error {
+            BoothError::NoStorage => &self.error_no_usb_device,
+            BoothError::StorageFull => &self.error_storage_full,
+            BoothError::WriteFailed => &self.error_write_failed,
+            BoothError::EmptyFile => &self.error_empty_file_written,
+            BoothError::CameraLost => &self.error_camera_lost,
+            BoothError::CaptureFailed => &self.error_capture_failed,
+            BoothError::DisplayFailed => &self.error_display_failed,
+            BoothError::NoTouchDevice => &self.error_no_touch_device,
+            BoothError::CalibrationFailed => &self.error_calibration_failed,
+            BoothError::Unknown => &self.unknown_error_message,
+        }
+    }
+
+    /// A random sentence of `done_sentences`
+    pub fn done_sentence(&self) -> String {
+        match self.done_sentences.len() {
+            0 => String::new(),
+            len => self.done_sentences[rand::random_range(0..len)].clone(),
+        }
+    }
+}
+
+/// The PIN of the admin menu. Hidden when the configuration is logged
+#[derive(serde::Deserialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct AdminPin(pub String);
+
+impl std::fmt::Debug for AdminPin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AdminPin(<hidden>)")
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct Config {
+    /// The language the booth starts in. Texts missing from another language
+    /// are taken from this one
+    pub language: String,
+    /// Languages the language button on the prompt screen switches between,
+    /// in order. When not set, `language` comes first and the other
+    /// languages of `texts` follow in alphabetical order
+    pub languages: Vec<String>,
+    /// Texts by language, e.g. `en`, `nl` and `fr`. Texts missing from all
+    /// of them are shown in English
+    pub texts: HashMap<String, serde_yaml::Mapping>,
+    // Texts from before `texts`, used as texts of `language`
+    #[serde(rename = "takePictureText")]
+    legacy_take_picture_text: Option<serde_yaml::Value>,
     #[serde(rename = "doneSentences")]
-    pub done_sentences: Vec<String>,
+    legacy_done_sentences: Option<serde_yaml::Value>,
+    #[serde(rename = "unknownErrorMessage")]
+    legacy_unknown_error_message: Option<serde_yaml::Value>,
+    #[serde(rename = "errorNoUsbDevice")]
+    legacy_error_no_usb_device: Option<serde_yaml::Value>,
+    #[serde(rename = "errorEmptyFileWritten")]
+    legacy_error_empty_file_written: Option<serde_yaml::Value>,
     /// Show done sentence for x seconds
     #[serde(rename = "doneShowTime")]
     pub done_show_time: u32,
 
     #[serde(rename = "bgColor")]
-    pub bg_color: u32,
+    pub bg_color: Color,
     #[serde(rename = "errorBgColor")]
-    pub error_bg_color: u32,
+    pub error_bg_color: Color,
+    /// Drawn over `bgColor` and `errorBgColor`
+    pub backgrounds: Backgrounds,
+    /// Palettes and backgrounds by name, e.g. one per event
+    pub themes: HashMap<String, Theme>,
+    /// The name of the theme in `themes` to use
+    pub theme: Option<String>,
+
+    /// Font files used for text, in order of preference. Characters missing
+    /// from a font are drawn with the next one. A built-in font is used as
+    /// last resort
+    pub fonts: Vec<String>,
+    /// Like `fonts`, but for emoji
+    #[serde(rename = "emojiFonts")]
+    pub emoji_fonts: Vec<String>,
 
     #[serde(rename = "textColor")]
-    pub text_color: u32,
+    pub text_color: Color,
+    /// Opacity of all text, from 0 (invisible) to 1
+    #[serde(rename = "textOpacity")]
+    pub text_opacity: f32,
+    /// e.g. `{ width: 4, color: black }`
+    #[serde(rename = "textOutline")]
+    pub text_outline: Option<TextOutline>,
+    /// e.g. `{ offsetX: 6, offsetY: 6, color: black, opacity: 0.6 }`
+    #[serde(rename = "textShadow")]
+    pub text_shadow: Option<TextShadow>,
 
-    #[serde(rename = "takePictureText")]
-    pub take_picture_text: String,
+    pub layouts: Layouts,
 
     /// The default text size
     #[serde(rename = "textSize")]
@@ -27,10 +673,37 @@ pub struct Config {
     #[serde(rename = "countdownTextSize")]
     pub countdown_text_size: f32,
 
-    /// Show the resulting image for (minimum) x seconds
+    /// Duration of the cross-fade between screens in milliseconds, 0 to
+    /// switch instantly
+    #[serde(rename = "transitionTime")]
+    pub transition_time: u32,
+
+    /// Show the resulting image for (minimum) x seconds, when `review` is
+    /// disabled
     #[serde(rename = "showImageTime")]
     pub show_image_time: u32,
+    /// Let guests keep, retake or delete each photo after it was taken
+    pub review: bool,
+    /// Seconds before the reviewed photo is kept automatically
+    #[serde(rename = "reviewTimeout")]
+    pub review_timeout: u32,
+    /// Thumbnails per row in the gallery
+    #[serde(rename = "galleryColumns")]
+    pub gallery_columns: usize,
+    /// Rows of thumbnails per page in the gallery
+    #[serde(rename = "galleryRows")]
+    pub gallery_rows: usize,
+    /// Seconds without any touch before the gallery goes back to the prompt
+    #[serde(rename = "galleryTimeout")]
+    pub gallery_timeout: u32,
 
+    /// `jpeg` or `png`
+    #[serde(rename = "imageFormat")]
+    pub image_format: PhotoFormat,
+    /// Name of the USB device to save photos on (e.g. `sda1`). The first
+    /// device found is used when not set or not connected
+    #[serde(rename = "storageDevice")]
+    pub storage_device: Option<String>,
     /// The sub path on the USB device where the images should be saved
     #[serde(rename = "storageSubPath")]
     pub storage_sub_path: Option<String>,
@@ -38,47 +711,447 @@ pub struct Config {
     /// Display error messages for x time
     #[serde(rename = "errorMessageTime")]
     pub error_message_time: u32,
-    #[serde(rename = "unknownErrorMessage")]
-    pub unknown_error_message: String,
-    #[serde(rename = "errorNoUsbDevice")]
-    pub error_no_usb_device: String,
-    #[serde(rename = "errorEmptyFileWritten")]
-    pub error_empty_file_witten: String,
+
+    /// Seconds without any touch on the prompt screen before the screen is
+    /// dimmed or blanked. Disabled when not set
+    #[serde(rename = "idleTimeout")]
+    pub idle_timeout: Option<u32>,
+    #[serde(rename = "idleAction")]
+    pub idle_action: IdleAction,
+    /// Backlight brightness in percent when dimmed
+    #[serde(rename = "idleBrightness")]
+    pub idle_brightness: u32,
+    /// Seconds without any touch on the prompt screen before recent photos
+    /// are shown to attract guests. Disabled when not set
+    #[serde(rename = "attractTimeout")]
+    pub attract_timeout: Option<u32>,
+    /// Number of recent photos shown in attract mode
+    #[serde(rename = "attractPhotoCount")]
+    pub attract_photo_count: usize,
+    /// Seconds each photo is shown in attract mode
+    #[serde(rename = "attractSlideTime")]
+    pub attract_slide_time: f32,
+    /// PIN for the admin menu, opened by holding a finger on the top left
+    /// corner of the prompt screen. The admin menu is disabled when not set
+    #[serde(rename = "adminPin")]
+    pub admin_pin: Option<AdminPin>,
+    /// Path to the sysfs backlight (e.g. /sys/class/backlight/10-0045). When
+    /// not set, the first backlight found is used
+    pub backlight: Option<String>,
 
     // System
+    /// `auto`, `drm` or `fbdev`. `auto` uses DRM and falls back to fbdev when
+    /// no DRM device is available
+    #[serde(rename = "displayBackend")]
+    pub display_backend: DisplayBackendKind,
+    #[serde(rename = "drmDevice")]
+    pub drm_device: String,
+    #[serde(rename = "fbDevice")]
+    pub fb_device: String,
+    /// Path of the touch device (e.g. /dev/input/by-id/usb-QDtech_MPI7003-event-if00).
+    /// All touch devices are detected automatically when not set
+    #[serde(rename = "inputDevice")]
+    pub input_device: Option<String>,
+    /// Only use touch devices whose name contains this string
+    #[serde(rename = "inputDeviceFilter")]
+    pub input_device_filter: Option<String>,
+    /// Keys and buttons mapped to actions, e.g. `space: startCapture`. Keys
+    /// are names from linux/input-event-codes.h (with or without `KEY_`) or
+    /// numeric key codes; `0` to `9` are the digit keys. Actions are `startCapture`, `keep`, `retake`,
+    /// `delete`, `openGallery`, `next`, `previous`, `back`, `nextLanguage`,
+    /// `openAdminMenu` and `calibrate`
+    #[serde(rename = "keyBindings")]
+    pub key_bindings: HashMap<String, InputAction>,
+    /// Correction for touch positions, written by the calibration screen.
+    /// Recalibrate after changing `rotation`
+    #[serde(rename = "touchCalibration")]
+    pub touch_calibration: Option<CalibrationMatrix>,
+    /// Clockwise rotation of the output in degrees (0, 90, 180 or 270). Use
+    /// this when the panel is mounted in portrait
+    pub rotation: Rotation,
+    /// Size of the camera preview. The size of the rotated screen when not
+    /// set; a preview of another size is centered and cropped
     #[serde(rename = "screenWidth")]
-    pub screen_width: u32,
+    pub screen_width: Option<u32>,
     #[serde(rename = "screenHeight")]
-    pub screen_height: u32,
+    pub screen_height: Option<u32>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            done_sentences: [
-                "All done!",
-                "You look great!",
-                "Come closer again",
-                "Looking good 😎",
-                "Curious to see the result?"
-            ].map(|s| s.into()).to_vec(),
+            language: "en".to_string(),
+            languages: Vec::new(),
+            texts: HashMap::new(),
+            legacy_take_picture_text: None,
+            legacy_done_sentences: None,
+            legacy_unknown_error_message: None,
+            legacy_error_no_usb_device: None,
+            legacy_error_empty_file_written: None,
             done_show_time: 4,
-            bg_color: 0xFF32a8a8,
-            error_bg_color: 0xFFed4e4e,
-            text_color: 0x00FFFFFF,
-            take_picture_text: "Touch to take a picture".to_string(),
+            bg_color: Color::rgb(0x32, 0xA8, 0xA8),
+            error_bg_color: Color::rgb(0xED, 0x4E, 0x4E),
+            backgrounds: Backgrounds::default(),
+            themes: HashMap::new(),
+            theme: None,
+            fonts: Vec::new(),
+            emoji_fonts: Vec::new(),
+            text_color: Color::WHITE,
+            text_opacity: 1.,
+            text_outline: None,
+            text_shadow: None,
+            layouts: Layouts::default(),
             text_size: 100.,
             countdown_text_size: 350.,
             countdown: 5,
+            transition_time: 300,
             show_image_time: 6,
+            review: true,
+            review_timeout: 10,
+            gallery_columns: 4,
+            gallery_rows: 3,
+            gallery_timeout: 60,
+            image_format: PhotoFormat::Jpeg,
+            storage_device: None,
             storage_sub_path: None,
             error_message_time: 8,
-            unknown_error_message: "Unkown error".to_string(),
-            error_no_usb_device: "No USB device connected".to_string(),
-            error_empty_file_witten: "File couldn't be written to (file is empty)".to_string(),
 
-            screen_width: 1920,
-            screen_height: 1080,
+            idle_timeout: None,
+            idle_action: IdleAction::Blank,
+            idle_brightness: 10,
+            attract_timeout: None,
+            attract_photo_count: 20,
+            attract_slide_time: 5.,
+            admin_pin: None,
+            backlight: None,
+
+            display_backend: DisplayBackendKind::Auto,
+            drm_device: "/dev/dri/by-path/platform-gpu-card".to_string(),
+            fb_device: "/dev/fb0".to_string(),
+            input_device: None,
+            input_device_filter: None,
+            key_bindings: [
+                ("space", InputAction::StartCapture),
+                ("enter", InputAction::StartCapture),
+                ("pagedown", InputAction::StartCapture),
+            ].map(|(key, action)| (key.to_string(), action)).into(),
+            touch_calibration: None,
+            rotation: Rotation::Rotate0,
+            screen_width: None,
+            screen_height: None,
+        }
+    }
+}
+
+impl Config {
+    /// The languages to switch between, see `languages`
+    pub fn languages(&self) -> Vec<String> {
+        if !self.languages.is_empty() {
+            return self.languages.clone();
+        }
+        let mut others: Vec<String> = self.texts.keys().filter(|language| **language != self.language).cloned().collect();
+        others.sort();
+        std::iter::once(self.language.clone()).chain(others).collect()
+    }
+
+    /// The texts in `language`. Texts missing from it are taken from the
+    /// default language, and from the built-in English texts after that
+    pub fn texts(&self, language: &str) -> Result<Texts> {
+        let mut merged = serde_yaml::Mapping::new();
+        for table in [self.texts.get(&self.language), self.texts.get(language)].into_iter().flatten() {
+            for (key, value) in table {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+        serde_yaml::from_value(serde_yaml::Value::Mapping(merged))
+            .map_err(|err| anyhow::anyhow!("Invalid texts for language '{}': {}", language, err))
+    }
+
+    /// Make relative file paths relative to `directory`, the folder of the
+    /// configuration file, instead of the working directory
+    fn resolve_paths(&mut self, directory: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = directory.join(&*path);
+            }
+        };
+
+        for font in self.fonts.iter_mut().chain(&mut self.emoji_fonts) {
+            if Path::new(font).is_relative() {
+                *font = directory.join(&*font).to_string_lossy().into_owned();
+            }
+        }
+        for (_, layout) in self.layouts.by_key_mut() {
+            for element in layout.iter_mut() {
+                if let LayoutElementKind::Image { path, .. } = &mut element.kind {
+                    resolve(path);
+                }
+            }
+        }
+        let themes = self.themes.values_mut().filter_map(|theme| theme.backgrounds.as_mut());
+        for backgrounds in std::iter::once(&mut self.backgrounds).chain(themes) {
+            let images = [&mut backgrounds.take_picture_prompt, &mut backgrounds.take_picture, &mut backgrounds.error];
+            for image in images.into_iter().flatten() {
+                resolve(&mut image.path);
+            }
+        }
+    }
+
+    /// Whether switching to `other` needs the display, camera, fonts or input
+    /// devices to be set up again. Other settings are used as they change
+    pub fn needs_restart(&self, other: &Config) -> bool {
+        self.display_backend != other.display_backend
+            || self.drm_device != other.drm_device
+            || self.fb_device != other.fb_device
+            || self.rotation != other.rotation
+            || self.screen_width != other.screen_width
+            || self.screen_height != other.screen_height
+            || self.fonts != other.fonts
+            || self.emoji_fonts != other.emoji_fonts
+            || self.input_device != other.input_device
+            || self.input_device_filter != other.input_device_filter
+            || self.key_bindings != other.key_bindings
+            || self.backlight != other.backlight
+            // The backlight is only looked for when idling is enabled
+            || self.idle_timeout.is_some() != other.idle_timeout.is_some()
+    }
+
+    /// Replace colors and backgrounds with those of the selected theme
+    pub fn apply_theme(&mut self) -> Result<()> {
+        let Some(name) = &self.theme else {
+            return Ok(());
+        };
+        let Some(theme) = self.themes.get(name).cloned() else {
+            anyhow::bail!("Unknown theme '{}', available themes: {:?}", name, self.themes.keys().collect::<Vec<_>>());
+        };
+
+        self.bg_color = theme.bg_color.unwrap_or(self.bg_color);
+        self.error_bg_color = theme.error_bg_color.unwrap_or(self.error_bg_color);
+        self.text_color = theme.text_color.unwrap_or(self.text_color);
+        if let Some(backgrounds) = theme.backgrounds {
+            self.backgrounds = backgrounds;
+        }
+        Ok(())
+    }
+
+    /// Check values and files, and apply the theme. Invalid values are
+    /// replaced by their defaults so the booth can run
+    pub fn validate(&mut self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        let defaults = Config::default();
+
+        check(&mut problems, "countdown", &mut self.countdown, defaults.countdown, |countdown| countdown >= 1, "must be at least 1");
+        check(&mut problems, "textSize", &mut self.text_size, defaults.text_size, |size| size > 0., "must be more than 0");
+        check(&mut problems, "countdownTextSize", &mut self.countdown_text_size, defaults.countdown_text_size, |size| size > 0., "must be more than 0");
+        check(&mut problems, "textOpacity", &mut self.text_opacity, defaults.text_opacity, |opacity| (0. ..=1.).contains(&opacity), "must be between 0 and 1");
+        check(&mut problems, "reviewTimeout", &mut self.review_timeout, defaults.review_timeout, |timeout| timeout >= 1, "must be at least 1");
+        check(&mut problems, "galleryColumns", &mut self.gallery_columns, defaults.gallery_columns, |columns| columns >= 1, "must be at least 1");
+        check(&mut problems, "galleryRows", &mut self.gallery_rows, defaults.gallery_rows, |rows| rows >= 1, "must be at least 1");
+        check(&mut problems, "attractPhotoCount", &mut self.attract_photo_count, defaults.attract_photo_count, |count| count >= 1, "must be at least 1");
+        check(&mut problems, "attractSlideTime", &mut self.attract_slide_time, defaults.attract_slide_time, |time| time > 0., "must be more than 0");
+        check(&mut problems, "idleBrightness", &mut self.idle_brightness, defaults.idle_brightness, |brightness| brightness <= 100, "must be at most 100");
+        for (key, size) in [("screenWidth", &mut self.screen_width), ("screenHeight", &mut self.screen_height)] {
+            if *size == Some(0) {
+                problems.push(ConfigProblem::new(key, "must be more than 0, using the size of the screen"));
+                *size = None;
+            }
+        }
+
+        if self.admin_pin.as_ref().is_some_and(|AdminPin(pin)| pin.is_empty() || !pin.chars().all(|c| c.is_ascii_digit())) {
+            problems.push(ConfigProblem::new("adminPin", "must only contain digits, the admin menu is disabled"));
+            self.admin_pin = None;
+        }
+
+        self.key_bindings.retain(|key, _| {
+            let known = keys::key_code(key).is_some();
+            if !known {
+                problems.push(ConfigProblem::new(format!("keyBindings.{}", key), "unknown key, ignored"));
+            }
+            known
+        });
+
+        for (index, font) in self.fonts.iter().enumerate() {
+            check_file(&mut problems, format!("fonts.{}", index), Path::new(font));
+        }
+        for (index, font) in self.emoji_fonts.iter().enumerate() {
+            check_file(&mut problems, format!("emojiFonts.{}", index), Path::new(font));
+        }
+
+        for (name, layout) in self.layouts.by_key_mut() {
+            for (index, element) in layout.iter_mut().enumerate() {
+                let key = format!("layouts.{}.{}", name, index);
+                if element.font_size.is_some_and(|size| size <= 0.) {
+                    problems.push(ConfigProblem::new(format!("{}.fontSize", key), "must be more than 0, using the text size of the screen"));
+                    element.font_size = None;
+                }
+                for (field, length) in [("width", &mut element.width), ("height", &mut element.height)] {
+                    if matches!(length, Length::Pixels(value) | Length::Percent(value) if *value < 0.) {
+                        problems.push(ConfigProblem::new(format!("{}.{}", key, field), "must not be negative, using 100%"));
+                        *length = Length::full();
+                    }
+                }
+                if let LayoutElementKind::Image { path, .. } = &element.kind {
+                    check_file(&mut problems, format!("{}.path", key), path);
+                }
+            }
+        }
+
+        check_backgrounds(&mut problems, "backgrounds", &mut self.backgrounds);
+        for (name, theme) in &mut self.themes {
+            if let Some(backgrounds) = &mut theme.backgrounds {
+                check_backgrounds(&mut problems, &format!("themes.{}.backgrounds", name), backgrounds);
+            }
+        }
+        if let Err(err) = self.apply_theme() {
+            problems.push(ConfigProblem::new("theme", format!("{}, using the default colors", err)));
+            self.theme = None;
         }
+
+        // Text colors used to be read in a different byte order, without
+        // alpha. Such values are now invisible
+        const TRANSPARENT: &str = "is fully transparent, numbers are 0xAARRGGBB";
+        check(&mut problems, "textColor", &mut self.text_color, defaults.text_color, |color| color.a > 0, TRANSPARENT);
+        if let Some(outline) = &mut self.text_outline {
+            check(&mut problems, "textOutline.color", &mut outline.color, Color::BLACK, |color| color.a > 0, TRANSPARENT);
+        }
+        if let Some(shadow) = &mut self.text_shadow {
+            check(&mut problems, "textShadow.color", &mut shadow.color, Color::BLACK, |color| color.a > 0, TRANSPARENT);
+        }
+
+        problems.extend(self.validate_texts());
+        problems
+    }
+
+    /// Check the texts of every language. Tables that can't be read are
+    /// left out, so their texts are taken from the default language
+    fn validate_texts(&mut self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+
+        let legacy_texts = [
+            ("takePictureText", self.legacy_take_picture_text.take()),
+            ("doneSentences", self.legacy_done_sentences.take()),
+            ("unknownErrorMessage", self.legacy_unknown_error_message.take()),
+            ("errorNoUsbDevice", self.legacy_error_no_usb_device.take()),
+            ("errorEmptyFileWritten", self.legacy_error_empty_file_written.take()),
+        ];
+        for (key, value) in legacy_texts {
+            let Some(value) = value else {
+                continue;
+            };
+            let table = self.texts.entry(self.language.clone()).or_default();
+            if table.contains_key(key) {
+                problems.push(ConfigProblem::new(key, format!("is deprecated, ignored as texts.{}.{} is set", self.language, key)));
+            } else {
+                problems.push(ConfigProblem::new(key, format!("is deprecated, move it to texts.{}.{}", self.language, key)));
+                table.insert(key.into(), value);
+            }
+        }
+
+        self.texts.retain(|language, table| {
+            let key = format!("texts.{}", language);
+            let empty_sentences = table.get("doneSentences").and_then(|sentences| sentences.as_sequence()).is_some_and(|sentences| sentences.is_empty());
+            if empty_sentences {
+                problems.push(ConfigProblem::new(format!("{}.doneSentences", key), "must not be empty, using the default sentences"));
+                table.remove("doneSentences");
+            }
+
+            let parsed: Result<Texts, _> = serde_ignored::deserialize(serde_yaml::Value::Mapping(table.clone()), |path| {
+                problems.push(ConfigProblem::new(format!("{}.{}", key, ignored_key(path)), "unknown text, ignored"));
+            });
+            match parsed {
+                Ok(_) => true,
+                Err(err) => {
+                    problems.push(ConfigProblem::new(key, format!("{}, using the texts of the default language", err)));
+                    false
+                },
+            }
+        });
+
+        let texts = &self.texts;
+        let default_language = &self.language;
+        self.languages.retain(|language| {
+            let known = texts.contains_key(language) || language == default_language;
+            if !known {
+                problems.push(ConfigProblem::new("languages", format!("no texts for '{}', left out", language)));
+            }
+            known
+        });
+
+        problems
+    }
+}
+
+/// Replace `value` by `default` when it isn't `valid`, with a problem
+/// explaining what is required
+fn check<T: Copy + Display>(problems: &mut Vec<ConfigProblem>, key: &str, value: &mut T, default: T, valid: impl Fn(T) -> bool, requirement: &str) {
+    if !valid(*value) {
+        problems.push(ConfigProblem::new(key, format!("{}, using {} instead of {}", requirement, default, value)));
+        *value = default;
+    }
+}
+
+fn check_file(problems: &mut Vec<ConfigProblem>, key: String, path: &Path) {
+    if !path.exists() {
+        problems.push(ConfigProblem::new(key, format!("{:?} not found", path)));
+    }
+}
+
+fn check_backgrounds(problems: &mut Vec<ConfigProblem>, key: &str, backgrounds: &mut Backgrounds) {
+    let images = [
+        ("takePicturePrompt", &mut backgrounds.take_picture_prompt),
+        ("takePicture", &mut backgrounds.take_picture),
+        ("error", &mut backgrounds.error),
+    ];
+    for (name, image) in images {
+        if let Some(image) = image {
+            check_file(problems, format!("{}.{}.path", key, name), &image.path);
+            check(problems, &format!("{}.{}.slideTime", key, name), &mut image.slide_time, BackgroundImage::DEFAULT_SLIDE_TIME, |time| time > 0., "must be more than 0");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(s: &str) -> serde_yaml::Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn state_overrides_nested_settings() {
+        let mut document = yaml("countdown: 3\ntexts:\n  en:\n    keepText: Keep\n    backText: Back");
+        merge(&mut document, yaml("countdown: 5\ntexts:\n  en:\n    keepText: Save"));
+        assert_eq!(document, yaml("countdown: 5\ntexts:\n  en:\n    keepText: Save\n    backText: Back"));
+    }
+
+    #[test]
+    fn settings_of_the_wrong_type_are_left_out() {
+        let text = "bgColor: red\ncountdown: soon\ntextSize: 40\n";
+        let mut problems = Vec::new();
+        let config = deserialize_config(yaml(text), text, &mut problems).unwrap();
+        assert_eq!(config.bg_color, Color::rgb(255, 0, 0));
+        assert_eq!(config.text_size, 40.);
+        assert_eq!(config.countdown, Config::default().countdown);
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert_eq!(problems[0].key, "countdown");
+        assert!(problems[0].message.contains("(line 2)"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn unknown_settings_are_reported() {
+        let text = "countdown: 3\ncountdwn: 5\n";
+        let mut problems = Vec::new();
+        deserialize_config(yaml(text), text, &mut problems).unwrap();
+        assert_eq!(problems, [ConfigProblem::new("countdwn", "unknown setting, ignored")]);
+    }
+
+    #[test]
+    fn leaf_keys_name_the_settings() {
+        let state = yaml("countdown: 5\ntexts:\n  en:\n    keepText: Save");
+        let names: Vec<String> = leaf_keys(&state).iter().map(|keys| key_name(keys)).collect();
+        assert_eq!(names, ["countdown", "texts.en.keepText"]);
+        assert_eq!(lookup(&state, &leaf_keys(&state)[1]), Some(&yaml("Save")));
+        assert!(leaf_keys(&yaml("5")).is_empty());
     }
 }
@@ -0,0 +1,286 @@
+use std::fs::{File, OpenOptions};
+use std::os::fd::AsRawFd;
+use std::os::raw::{c_ulong, c_void};
+
+use anyhow::{anyhow, Result};
+use log::*;
+
+use super::{BackBuffer, DisplayBackend};
+
+// ioctls from linux/fb.h
+const FBIOGET_VSCREENINFO: c_ulong = 0x4600;
+const FBIOPUT_VSCREENINFO: c_ulong = 0x4601;
+const FBIOGET_FSCREENINFO: c_ulong = 0x4602;
+const FBIOPAN_DISPLAY: c_ulong = 0x4606;
+const FBIOBLANK: c_ulong = 0x4611;
+
+const FB_BLANK_UNBLANK: c_ulong = 0;
+const FB_BLANK_POWERDOWN: c_ulong = 4;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct FbBitfield {
+    offset: u32,
+    length: u32,
+    msb_right: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct FbVarScreeninfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    pixclock: u32,
+    left_margin: u32,
+    right_margin: u32,
+    upper_margin: u32,
+    lower_margin: u32,
+    hsync_len: u32,
+    vsync_len: u32,
+    sync: u32,
+    vmode: u32,
+    rotate: u32,
+    colorspace: u32,
+    reserved: [u32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct FbFixScreeninfo {
+    id: [u8; 16],
+    smem_start: c_ulong,
+    smem_len: u32,
+    type_: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: c_ulong,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+/// Display using the legacy framebuffer device (/dev/fbX), for systems
+/// without a working KMS driver
+pub struct FbDisplay {
+    file: File,
+    var_info: FbVarScreeninfo,
+    original_var_info: FbVarScreeninfo,
+    line_length: usize,
+    map: *mut u8,
+    map_len: usize,
+    /// The virtual screen holds two frames and the back buffer is shown by
+    /// panning
+    panning: bool,
+    current_buffer: usize,
+    /// XRGB8888 back buffer, used when the framebuffer can't be drawn in
+    /// directly. It is converted and copied to the framebuffer on swap
+    shadow: Option<Vec<u8>>,
+}
+
+impl FbDisplay {
+    pub fn new(device: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true).write(true)
+            .open(device)?;
+        let fd = file.as_raw_fd();
+
+        let mut var_info = FbVarScreeninfo::default();
+        if unsafe { libc::ioctl(fd, FBIOGET_VSCREENINFO as _, &mut var_info) } < 0 {
+            return Err(anyhow!("Couldn't get variable screen info of {}: {}", device, std::io::Error::last_os_error()));
+        }
+        let original_var_info = var_info;
+
+        if !matches!(var_info.bits_per_pixel, 16 | 24 | 32) {
+            anyhow::bail!("Unsupported framebuffer depth of {} bits per pixel", var_info.bits_per_pixel);
+        }
+
+        // Try to get a virtual screen twice the height of the panel to pan between
+        if var_info.yres_virtual < var_info.yres * 2 {
+            let mut double = var_info;
+            double.yres_virtual = var_info.yres * 2;
+            double.yoffset = 0;
+            if unsafe { libc::ioctl(fd, FBIOPUT_VSCREENINFO as _, &mut double) } == 0 {
+                var_info = double;
+            } else {
+                debug!("Framebuffer doesn't support a double height virtual screen");
+            }
+        }
+
+        let mut fix_info = FbFixScreeninfo::default();
+        if unsafe { libc::ioctl(fd, FBIOGET_FSCREENINFO as _, &mut fix_info) } < 0 {
+            return Err(anyhow!("Couldn't get fixed screen info of {}: {}", device, std::io::Error::last_os_error()));
+        }
+
+        let line_length = fix_info.line_length as usize;
+        let frame_len = line_length * var_info.yres as usize;
+        let map_len = fix_info.smem_len as usize;
+        let panning = var_info.yres_virtual >= var_info.yres * 2
+            && fix_info.ypanstep != 0
+            && map_len >= frame_len * 2;
+
+        let map = unsafe { libc::mmap(
+            std::ptr::null_mut(),
+            map_len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0
+        ) };
+        if map == libc::MAP_FAILED {
+            anyhow::bail!("Failed to map framebuffer to memory");
+        }
+
+        let mut display = FbDisplay {
+            file,
+            var_info,
+            original_var_info,
+            line_length,
+            map: map as *mut u8,
+            map_len,
+            panning,
+            current_buffer: 0,
+            shadow: None,
+        };
+
+        if !(panning && display.is_xrgb8888()) {
+            display.shadow = Some(vec![0; (var_info.xres as usize) * (var_info.yres as usize) * 4]);
+        }
+
+        info!(
+            "Framebuffer {} {}x{} {}bpp (panning: {}, shadow buffer: {})",
+            device, var_info.xres, var_info.yres, var_info.bits_per_pixel,
+            display.panning, display.shadow.is_some()
+        );
+
+        Ok(display)
+    }
+
+    /// Whether the framebuffer uses the same layout as the rest of the application
+    fn is_xrgb8888(&self) -> bool {
+        let var = &self.var_info;
+        var.bits_per_pixel == 32
+            && var.red.offset == 16 && var.red.length == 8
+            && var.green.offset == 8 && var.green.length == 8
+            && var.blue.offset == 0 && var.blue.length == 8
+            && self.line_length == var.xres as usize * 4
+    }
+
+    fn frame_len(&self) -> usize {
+        self.line_length * self.var_info.yres as usize
+    }
+
+    fn back_index(&self) -> usize {
+        if self.panning && self.current_buffer == 0 { 1 } else { 0 }
+    }
+
+    /// The mapped memory of frame `index` of the virtual screen
+    fn frame_mut(&mut self, index: usize) -> &mut [u8] {
+        let frame_len = self.frame_len();
+        assert!((index + 1) * frame_len <= self.map_len);
+        unsafe { std::slice::from_raw_parts_mut(self.map.add(index * frame_len), frame_len) }
+    }
+
+    /// Convert the shadow buffer into the framebuffer's pixel format
+    fn copy_shadow(&mut self, index: usize) {
+        let Some(shadow) = self.shadow.take() else {
+            return;
+        };
+
+        let var = self.var_info;
+        let line_length = self.line_length;
+        let bytes_per_pixel = (var.bits_per_pixel / 8) as usize;
+        let width = var.xres as usize;
+        let frame = self.frame_mut(index);
+
+        let channel = |value: u32, field: &FbBitfield| -> u32 {
+            (value >> (8 - field.length.min(8))) << field.offset
+        };
+
+        for (y, row) in shadow.chunks_exact(width * 4).enumerate() {
+            let dst_row = &mut frame[y * line_length..y * line_length + width * bytes_per_pixel];
+            for (src, dst) in row.chunks_exact(4).zip(dst_row.chunks_exact_mut(bytes_per_pixel)) {
+                let (b, g, r) = (src[0] as u32, src[1] as u32, src[2] as u32);
+                let pixel = channel(r, &var.red) | channel(g, &var.green) | channel(b, &var.blue);
+                dst.copy_from_slice(&pixel.to_le_bytes()[..bytes_per_pixel]);
+            }
+        }
+
+        self.shadow = Some(shadow);
+    }
+}
+
+impl DisplayBackend for FbDisplay {
+    fn size(&self) -> (u16, u16) {
+        (self.var_info.xres as u16, self.var_info.yres as u16)
+    }
+
+    fn stride(&self) -> usize {
+        if self.shadow.is_some() {
+            self.var_info.xres as usize
+        } else {
+            self.line_length / 4
+        }
+    }
+
+    fn back_buffer_mut<'a>(&'a mut self) -> Result<BackBuffer<'a>> {
+        if self.shadow.is_some() {
+            return Ok(BackBuffer::Canvas(self.shadow.as_mut().unwrap().as_mut_slice()));
+        }
+
+        let back_index = self.back_index();
+        Ok(BackBuffer::Canvas(self.frame_mut(back_index)))
+    }
+
+    fn swap_buffers(&mut self) -> Result<()> {
+        let back_index = self.back_index();
+        self.copy_shadow(back_index);
+
+        if self.panning {
+            self.var_info.yoffset = self.var_info.yres * back_index as u32;
+            if unsafe { libc::ioctl(self.file.as_raw_fd(), FBIOPAN_DISPLAY as _, &mut self.var_info) } < 0 {
+                return Err(anyhow!("Couldn't pan framebuffer: {}", std::io::Error::last_os_error()));
+            }
+            self.current_buffer = back_index;
+        }
+
+        Ok(())
+    }
+
+    fn set_power(&self, on: bool) -> Result<()> {
+        let blank = if on { FB_BLANK_UNBLANK } else { FB_BLANK_POWERDOWN };
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), FBIOBLANK as _, blank) } < 0 {
+            return Err(anyhow!("Couldn't blank framebuffer: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FbDisplay {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.map as *mut c_void, self.map_len) };
+
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), FBIOPUT_VSCREENINFO as _, &mut self.original_var_info) } < 0 {
+            warn!("Couldn't restore framebuffer settings: {}", std::io::Error::last_os_error());
+        }
+    }
+}
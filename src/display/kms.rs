@@ -1,13 +1,14 @@
 use std::fs::{File, OpenOptions};
 use std::os::fd::AsFd;
-use std::os::raw::c_void;
 
 use anyhow::{anyhow, Result};
-use drm::buffer::DrmFourcc;
-use drm::control::dumbbuffer::{DumbBuffer, DumbMapping};
+use drm::buffer::{Buffer, DrmFourcc};
+use drm::control::dumbbuffer::DumbBuffer;
 use drm::control::{framebuffer, Device, FbCmd2Flags};
 use log::*;
 
+use super::{BackBuffer, DisplayBackend};
+
 /// GPU DRM
 pub struct Card(File);
 
@@ -19,7 +20,8 @@ impl AsFd for Card {
     }
 }
 
-pub struct Display {
+/// Display using kernel mode setting
+pub struct DrmDisplay {
     card: Card,
     connector: drm::control::connector::Handle,
     crtc: drm::control::crtc::Handle,
@@ -32,7 +34,7 @@ pub struct Display {
     current_buffer: usize,
 }
 
-impl Display {
+impl DrmDisplay {
     pub fn new(gpu_card: &str, format: DrmFourcc, depth: u32, bpp: u32) -> Result<Self> {
         // Retrieve DRM resources
         let gpu_card = OpenOptions::new()
@@ -158,54 +160,46 @@ impl Display {
         self.framebuffers[self.current_buffer]
     }
 
-    pub fn back_buffer_mut<'a>(&'a mut self) -> Result<DumbMapping<'a>> {
-        let buffer = &mut self.buffers[if self.current_buffer == 0 { 1 } else { 0 }];
-        let map = self.card.map_dumb_buffer(buffer)?;
-        Ok(map)
+    fn back_index(&self) -> usize {
+        if self.current_buffer == 0 { 1 } else { 0 }
     }
+}
 
-    /// Copy dma framebuffer from file descriptor to the back buffer
-    /// SAFETY: `fb_ptr` must be valid and of length `size`
-    pub unsafe fn copy_dma_buf(&mut self, fb_ptr: *mut c_void, size: usize) -> Result<()> {
-        // let mut buffer_map = self.card.map_dumb_buffer(self.back_buffer_mut())?;
-        let mut buffer_map = self.back_buffer_mut()?;
-
-        let data = unsafe { std::slice::from_raw_parts(fb_ptr as *const u8, size) };
+impl DisplayBackend for DrmDisplay {
+    fn size(&self) -> (u16, u16) {
+        self.size
+    }
 
-        let copy_size = size.min(buffer_map.len());
-        buffer_map[..copy_size].copy_from_slice(&data[..copy_size]);
+    fn stride(&self) -> usize {
+        (self.buffers[self.back_index()].pitch() / 4) as usize
+    }
 
-        Ok(())
+    fn back_buffer_mut<'a>(&'a mut self) -> Result<BackBuffer<'a>> {
+        let back_index = self.back_index();
+        let buffer = &mut self.buffers[back_index];
+        let map = self.card.map_dumb_buffer(buffer)?;
+        Ok(BackBuffer::Mapped(map))
     }
 
-    pub fn swap_buffers(&mut self) -> Result<()> {
+    fn swap_buffers(&mut self) -> Result<()> {
         self.current_buffer = if self.current_buffer == 1 { 0 } else { 1 };
         self.set_crtc(Some(self.front_framebuffer()))
     }
 
-    pub fn size(&self) -> (u16, u16) {
-        self.size
-    }
+    /// Turn the panel on or off using the connector's DPMS property
+    fn set_power(&self, on: bool) -> Result<()> {
+        const DPMS_ON: u64 = 0;
+        const DPMS_OFF: u64 = 3;
 
-    #[allow(unused)]
-    fn buffer_size_u8(&self) -> usize {
-        (self.size.0 as usize) * (self.size.1 as usize) * 4
-    }
-
-    fn buffer_size_u32(&self) -> usize {
-        (self.size.0 as usize) * (self.size.1 as usize)
-    }
+        let properties = self.card.get_properties(self.connector)?.as_hashmap(&self.card)?;
+        let dpms = properties.get("DPMS").ok_or_else(|| anyhow!("Connector has no DPMS property"))?;
+        self.card.set_property(self.connector, dpms.handle(), if on { DPMS_ON } else { DPMS_OFF })?;
 
-    pub fn clear(&mut self, color: u32) -> Result<()> {
-        let buffer_size = self.buffer_size_u32();
-        let mut back_buffer = self.back_buffer_mut()?;
-        let back_buffer: &mut [u32] = unsafe { std::slice::from_raw_parts_mut(back_buffer.as_mut_ptr() as *mut u32, buffer_size) };
-        back_buffer[..].fill(color);
         Ok(())
     }
 }
 
-impl Drop for Display {
+impl Drop for DrmDisplay {
     fn drop(&mut self) {
         for buffer in self.buffers.into_iter() {
             self.card.destroy_dumb_buffer(buffer).unwrap();
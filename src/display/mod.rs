@@ -0,0 +1,432 @@
+use std::ops::{Deref, DerefMut};
+use std::os::raw::c_void;
+
+use anyhow::{Context, Result};
+use drm::buffer::DrmFourcc;
+use drm::control::dumbbuffer::DumbMapping;
+use log::*;
+
+use crate::color::Color;
+use crate::error::BoothError;
+
+mod kms;
+mod fbdev;
+
+pub use kms::*;
+pub use fbdev::*;
+
+/// Clockwise rotation of the rendered output relative to the panel's native
+/// orientation
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "u32")]
+pub enum Rotation {
+    #[default]
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl TryFrom<u32> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u32) -> std::result::Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::Rotate0),
+            90 => Ok(Rotation::Rotate90),
+            180 => Ok(Rotation::Rotate180),
+            270 => Ok(Rotation::Rotate270),
+            _ => Err(format!("Invalid rotation {}, expected one of 0, 90, 180 or 270", degrees)),
+        }
+    }
+}
+
+impl Rotation {
+    /// Whether width and height are swapped
+    pub fn is_transposed(&self) -> bool {
+        matches!(self, Rotation::Rotate90 | Rotation::Rotate270)
+    }
+
+    /// The size of the rotated (logical) screen for a panel of `physical_size`
+    pub fn logical_size<T>(&self, physical_size: (T, T)) -> (T, T) {
+        if self.is_transposed() {
+            (physical_size.1, physical_size.0)
+        } else {
+            physical_size
+        }
+    }
+
+    /// Map a point on the panel to a point on the rotated (logical) screen
+    pub fn physical_to_logical(&self, pos: (f64, f64), physical_size: (f64, f64)) -> (f64, f64) {
+        let (x, y) = pos;
+        let (w, h) = physical_size;
+        match self {
+            Rotation::Rotate0 => (x, y),
+            Rotation::Rotate90 => (y, w - x),
+            Rotation::Rotate180 => (w - x, h - y),
+            Rotation::Rotate270 => (h - y, x),
+        }
+    }
+}
+
+/// Which kind of device to display on
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DisplayBackendKind {
+    /// Use DRM, fall back to fbdev when no DRM device can be opened
+    #[default]
+    Auto,
+    Drm,
+    Fbdev,
+}
+
+/// A buffer to draw a frame in. Pixels are XRGB8888
+pub enum BackBuffer<'a> {
+    /// A mapped DRM dumb buffer
+    Mapped(DumbMapping<'a>),
+    /// A buffer in memory
+    Canvas(&'a mut [u8]),
+}
+
+impl<'a> Deref for BackBuffer<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            BackBuffer::Mapped(mapping) => mapping,
+            BackBuffer::Canvas(canvas) => canvas,
+        }
+    }
+}
+
+impl<'a> DerefMut for BackBuffer<'a> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            BackBuffer::Mapped(mapping) => mapping,
+            BackBuffer::Canvas(canvas) => canvas,
+        }
+    }
+}
+
+impl<'a> AsMut<[u8]> for BackBuffer<'a> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+/// A double buffered output device
+pub trait DisplayBackend {
+    /// Size of the panel in its native orientation
+    fn size(&self) -> (u16, u16);
+    /// Number of pixels in a row of the back buffer
+    fn stride(&self) -> usize;
+    /// The buffer to draw the next frame in, in the panel's native orientation
+    fn back_buffer_mut<'a>(&'a mut self) -> Result<BackBuffer<'a>>;
+    /// Show the back buffer
+    fn swap_buffers(&mut self) -> Result<()>;
+    /// Turn the panel on or off
+    fn set_power(&self, on: bool) -> Result<()>;
+}
+
+pub struct Display {
+    backend: Box<dyn DisplayBackend>,
+    /// Size of the rotated screen
+    size: (u16, u16),
+    rotation: Rotation,
+    /// Render target when the output is rotated or the back buffer has padding.
+    /// Kept as pixels so they can be read as `u32` without alignment issues
+    canvas: Option<Vec<u32>>,
+}
+
+impl Display {
+    pub fn new(backend: Box<dyn DisplayBackend>, rotation: Rotation) -> Self {
+        let physical_size = backend.size();
+        let size = rotation.logical_size(physical_size);
+
+        let canvas = if rotation == Rotation::Rotate0 && backend.stride() == physical_size.0 as usize {
+            None
+        } else {
+            info!("Drawing to intermediate buffer (rotation: {:?}, stride: {})", rotation, backend.stride());
+            Some(vec![0; (size.0 as usize) * (size.1 as usize)])
+        };
+
+        Display {
+            backend,
+            size,
+            rotation,
+            canvas,
+        }
+    }
+
+    /// Open the display at `drm_device` or `fb_device` depending on `kind`
+    pub fn open(kind: DisplayBackendKind, drm_device: &str, fb_device: &str, rotation: Rotation) -> Result<Self> {
+        let open_drm = || -> Result<Box<dyn DisplayBackend>> {
+            Ok(Box::new(DrmDisplay::new(drm_device, DrmFourcc::Xrgb8888, 24, 32)?))
+        };
+        let open_fbdev = || -> Result<Box<dyn DisplayBackend>> {
+            Ok(Box::new(FbDisplay::new(fb_device)?))
+        };
+
+        let backend = match kind {
+            DisplayBackendKind::Drm => open_drm(),
+            DisplayBackendKind::Fbdev => open_fbdev(),
+            DisplayBackendKind::Auto => open_drm().or_else(|err| {
+                warn!("Couldn't open DRM device {}, falling back to {}: {:?}", drm_device, fb_device, err);
+                open_fbdev()
+            }),
+        }.context(BoothError::DisplayFailed)?;
+
+        Ok(Self::new(backend, rotation))
+    }
+
+    /// The buffer to draw the next frame in. Its layout is that of the rotated
+    /// screen (see `size`)
+    pub fn back_buffer_mut<'a>(&'a mut self) -> Result<BackBuffer<'a>> {
+        if let Some(canvas) = self.canvas.as_mut() {
+            return Ok(BackBuffer::Canvas(pixel_bytes_mut(canvas)));
+        }
+
+        self.backend.back_buffer_mut().context(BoothError::DisplayFailed)
+    }
+
+    /// Copy the canvas into the back buffer of the backend
+    fn blit_canvas(&mut self) -> Result<()> {
+        let Some(canvas) = &self.canvas else {
+            return Ok(());
+        };
+
+        let stride = self.backend.stride();
+        let (lw, lh) = (self.size.0 as usize, self.size.1 as usize);
+        let (pw, ph) = (self.backend.size().0 as usize, self.backend.size().1 as usize);
+        let rotation = self.rotation;
+
+        // The back buffer is written byte wise, it isn't guaranteed to be
+        // aligned for `u32`
+        let mut dst = self.backend.back_buffer_mut()?;
+
+        for ly in 0..lh {
+            let row = &canvas[ly * lw..(ly + 1) * lw];
+            for (lx, &pixel) in row.iter().enumerate() {
+                let (px, py) = match rotation {
+                    Rotation::Rotate0 => (lx, ly),
+                    Rotation::Rotate90 => (pw - 1 - ly, lx),
+                    Rotation::Rotate180 => (pw - 1 - lx, ph - 1 - ly),
+                    Rotation::Rotate270 => (ly, ph - 1 - lx),
+                };
+                let offset = (py * stride + px) * 4;
+                dst[offset..offset + 4].copy_from_slice(&pixel.to_ne_bytes());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copy dma framebuffer from file descriptor to the back buffer. A frame
+    /// of another size than the screen is centered and cropped
+    /// SAFETY: `fb_ptr` must be valid and of length `size`
+    pub unsafe fn copy_dma_buf(&mut self, fb_ptr: *mut c_void, size: usize, layout: FrameLayout) -> Result<()> {
+        let screen_size = (self.size.0 as usize, self.size.1 as usize);
+        let mut buffer_map = self.back_buffer_mut()?;
+
+        let data = unsafe { std::slice::from_raw_parts(fb_ptr as *const u8, size) };
+        copy_frame(&mut buffer_map, screen_size, data, layout);
+
+        Ok(())
+    }
+
+    pub fn swap_buffers(&mut self) -> Result<()> {
+        self.blit_canvas().context(BoothError::DisplayFailed)?;
+        self.backend.swap_buffers().context(BoothError::DisplayFailed)
+    }
+
+    /// Turn the panel on or off
+    pub fn set_power(&self, on: bool) -> Result<()> {
+        self.backend.set_power(on)
+    }
+
+    /// Size of the rotated screen. All drawing happens in this coordinate space
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    /// Size of the panel in its native orientation
+    pub fn physical_size(&self) -> (u16, u16) {
+        self.backend.size()
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    fn buffer_size_u8(&self) -> usize {
+        (self.size.0 as usize) * (self.size.1 as usize) * 4
+    }
+
+    pub fn clear(&mut self, color: Color) -> Result<()> {
+        let buffer_size = self.buffer_size_u8();
+        let pixel = color.to_xrgb8888();
+        let mut back_buffer = self.back_buffer_mut()?;
+        for dst in back_buffer[..buffer_size].chunks_exact_mut(4) {
+            dst.copy_from_slice(&pixel);
+        }
+        Ok(())
+    }
+}
+
+/// Size of a camera frame in pixels, and the length of its rows in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLayout {
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+}
+
+/// Copy the XRGB8888 `frame` to the middle of `buffer`, a screen of
+/// `screen_size` without padding. Parts that don't fit are cropped, the
+/// screen around a smaller frame is black
+fn copy_frame(buffer: &mut [u8], screen_size: (usize, usize), frame: &[u8], layout: FrameLayout) {
+    let columns = layout.width.min(screen_size.0);
+    let rows = layout.height.min(screen_size.1);
+    if columns < screen_size.0 || rows < screen_size.1 {
+        buffer.fill(0);
+    }
+    let (src_x, src_y) = ((layout.width - columns) / 2, (layout.height - rows) / 2);
+    let (dst_x, dst_y) = ((screen_size.0 - columns) / 2, (screen_size.1 - rows) / 2);
+
+    for row in 0..rows {
+        let src = (src_y + row) * layout.stride + src_x * 4;
+        let dst = ((dst_y + row) * screen_size.0 + dst_x) * 4;
+        let (Some(src), Some(dst)) = (frame.get(src..src + columns * 4), buffer.get_mut(dst..dst + columns * 4)) else {
+            break;
+        };
+        dst.copy_from_slice(src);
+    }
+}
+
+/// The bytes of `pixels`, in memory order
+fn pixel_bytes_mut(pixels: &mut [u32]) -> &mut [u8] {
+    // SAFETY: the bytes cover exactly the memory of `pixels`, and `u8` has
+    // no alignment requirement or invalid values
+    unsafe { std::slice::from_raw_parts_mut(pixels.as_mut_ptr().cast::<u8>(), std::mem::size_of_val(pixels)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A panel in memory, with padding at the end of each row
+    struct MemoryDisplay {
+        size: (u16, u16),
+        stride: usize,
+        buffer: Vec<u8>,
+    }
+
+    impl DisplayBackend for MemoryDisplay {
+        fn size(&self) -> (u16, u16) {
+            self.size
+        }
+
+        fn stride(&self) -> usize {
+            self.stride
+        }
+
+        fn back_buffer_mut<'a>(&'a mut self) -> Result<BackBuffer<'a>> {
+            Ok(BackBuffer::Canvas(&mut self.buffer))
+        }
+
+        fn swap_buffers(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn set_power(&self, _on: bool) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    const ROTATIONS: [Rotation; 4] = [Rotation::Rotate0, Rotation::Rotate90, Rotation::Rotate180, Rotation::Rotate270];
+
+    #[test]
+    fn touches_map_to_the_pixels_drawn_there() {
+        let (pw, ph, stride) = (4usize, 3usize, 6usize);
+        for rotation in ROTATIONS {
+            let backend = MemoryDisplay { size: (pw as u16, ph as u16), stride, buffer: vec![0; stride * ph * 4] };
+            let mut display = Display::new(Box::new(backend), rotation);
+            let (lw, lh) = (display.size().0 as usize, display.size().1 as usize);
+
+            // Number every logical pixel, starting at 1 so padding stays 0
+            {
+                let mut canvas = display.back_buffer_mut().unwrap();
+                for (index, pixel) in canvas.chunks_exact_mut(4).enumerate() {
+                    pixel.copy_from_slice(&(index as u32 + 1).to_ne_bytes());
+                }
+            }
+            display.blit_canvas().unwrap();
+
+            let panel = display.backend.back_buffer_mut().unwrap();
+            for py in 0..ph {
+                for px in 0..pw {
+                    let offset = (py * stride + px) * 4;
+                    let index = u32::from_ne_bytes(panel[offset..offset + 4].try_into().unwrap()) as usize - 1;
+                    let (lx, ly) = (index % lw, index / lw);
+                    assert!(ly < lh);
+
+                    // The center of the physical pixel is the center of the
+                    // logical pixel drawn there
+                    let logical = rotation.physical_to_logical((px as f64 + 0.5, py as f64 + 0.5), (pw as f64, ph as f64));
+                    assert_eq!(logical, (lx as f64 + 0.5, ly as f64 + 0.5), "{:?} at ({}, {})", rotation, px, py);
+                }
+            }
+        }
+    }
+
+    /// A frame whose pixels are numbered from 1, row by row, with `padding`
+    /// bytes at the end of each row
+    fn numbered_frame(width: usize, height: usize, padding: usize) -> (Vec<u8>, FrameLayout) {
+        let stride = width * 4 + padding;
+        let mut frame = vec![0xEE; stride * height];
+        for y in 0..height {
+            for x in 0..width {
+                let offset = y * stride + x * 4;
+                frame[offset..offset + 4].copy_from_slice(&((y * width + x) as u32 + 1).to_ne_bytes());
+            }
+        }
+        (frame, FrameLayout { width, height, stride })
+    }
+
+    fn pixels(buffer: &[u8]) -> Vec<u32> {
+        buffer.chunks_exact(4).map(|pixel| u32::from_ne_bytes(pixel.try_into().unwrap())).collect()
+    }
+
+    #[test]
+    fn frames_of_the_screen_size_are_copied_without_padding() {
+        let (frame, layout) = numbered_frame(3, 2, 8);
+        let mut buffer = vec![0; 3 * 2 * 4];
+        copy_frame(&mut buffer, (3, 2), &frame, layout);
+        assert_eq!(pixels(&buffer), [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn larger_frames_are_cropped_to_the_middle() {
+        // A landscape frame on a portrait screen
+        let (frame, layout) = numbered_frame(4, 2, 4);
+        let mut buffer = vec![0; 2 * 4 * 4];
+        copy_frame(&mut buffer, (2, 4), &frame, layout);
+        assert_eq!(pixels(&buffer), [0, 0, 2, 3, 6, 7, 0, 0]);
+    }
+
+    #[test]
+    fn smaller_frames_are_centered_on_black() {
+        let (frame, layout) = numbered_frame(1, 1, 0);
+        let mut buffer = vec![0xFF; 3 * 3 * 4];
+        copy_frame(&mut buffer, (3, 3), &frame, layout);
+        assert_eq!(pixels(&buffer), [0, 0, 0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn logical_size_swaps_for_portrait() {
+        assert_eq!(Rotation::Rotate0.logical_size((800, 480)), (800, 480));
+        assert_eq!(Rotation::Rotate90.logical_size((800, 480)), (480, 800));
+        assert_eq!(Rotation::Rotate180.logical_size((800, 480)), (800, 480));
+        assert_eq!(Rotation::Rotate270.logical_size((800, 480)), (480, 800));
+    }
+}
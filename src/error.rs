@@ -0,0 +1,82 @@
+use std::fmt::Display;
+
+/// What went wrong, as far as guests need to know. Errors are tagged with it
+/// as `anyhow` context, e.g. `.context(BoothError::CameraLost)`, so the
+/// technical cause stays in the error chain for the log while the screen
+/// shows the configured message for the variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BoothError {
+    /// No USB storage device is connected
+    NoStorage,
+    /// The storage device has no space left
+    StorageFull,
+    /// A photo couldn't be written to the storage device
+    WriteFailed,
+    /// A photo was written, but the file is empty
+    EmptyFile,
+    /// The camera isn't found or stopped responding
+    CameraLost,
+    /// Taking the photo failed
+    CaptureFailed,
+    /// The screen couldn't be set up or drawn to
+    DisplayFailed,
+    /// No touch device is connected or it stopped responding
+    NoTouchDevice,
+    /// The touch calibration couldn't be computed
+    CalibrationFailed,
+    Unknown,
+}
+
+impl BoothError {
+    /// The variant `err` was tagged with, `Unknown` when it wasn't
+    pub fn of(err: &anyhow::Error) -> BoothError {
+        err.downcast_ref::<BoothError>().copied().unwrap_or(BoothError::Unknown)
+    }
+
+    /// Tag a failed write with `StorageFull` when there is no space left,
+    /// `WriteFailed` otherwise
+    pub fn writing(err: std::io::Error) -> anyhow::Error {
+        let kind = match err.kind() {
+            std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded => BoothError::StorageFull,
+            _ => BoothError::WriteFailed,
+        };
+        anyhow::Error::new(err).context(kind)
+    }
+
+    /// Shown with the message so the operator can tell errors apart, and
+    /// find them in the log
+    pub fn code(&self) -> &'static str {
+        match self {
+            BoothError::NoStorage => "E101",
+            BoothError::StorageFull => "E102",
+            BoothError::WriteFailed => "E103",
+            BoothError::EmptyFile => "E104",
+            BoothError::CameraLost => "E201",
+            BoothError::CaptureFailed => "E202",
+            BoothError::DisplayFailed => "E301",
+            BoothError::NoTouchDevice => "E401",
+            BoothError::CalibrationFailed => "E402",
+            BoothError::Unknown => "E999",
+        }
+    }
+}
+
+impl Display for BoothError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            BoothError::NoStorage => "No storage device connected",
+            BoothError::StorageFull => "Storage device is full",
+            BoothError::WriteFailed => "Couldn't write to the storage device",
+            BoothError::EmptyFile => "Written file is empty",
+            BoothError::CameraLost => "Camera unavailable",
+            BoothError::CaptureFailed => "Capture failed",
+            BoothError::DisplayFailed => "Display failed",
+            BoothError::NoTouchDevice => "Touch device unavailable",
+            BoothError::CalibrationFailed => "Calibration failed",
+            BoothError::Unknown => "Unknown error",
+        };
+        write!(f, "{} ({})", description, self.code())
+    }
+}
+
+impl std::error::Error for BoothError {}
@@ -1,9 +1,10 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use log::*;
 
-use super::usb;
+use super::{Thumbnails, usb};
+use crate::error::BoothError;
 
 /// This FileManager only writes images
 pub struct FileManager {
@@ -13,9 +14,22 @@ pub struct FileManager {
 
 impl FileManager {
     pub fn new(location: PathBuf) -> Result<FileManager> {
-        let dir_entries = std::fs::read_dir(&location)?;
+        let max_index = Self::images(&location)?.into_iter()
+            .map(|(index, _)| index)
+            .max()
+            .unwrap_or(0);
 
-        let mut max_index = 0;
+        Ok(FileManager {
+            write_location: location,
+            max_index
+        })
+    }
+
+    /// The images written to `location`, with their index
+    fn images(location: &PathBuf) -> Result<Vec<(usize, PathBuf)>> {
+        let dir_entries = std::fs::read_dir(location).context(BoothError::NoStorage)?;
+
+        let mut images = Vec::new();
         for dir_entry in dir_entries {
             let dir_entry = dir_entry?;
             if !dir_entry.path().is_file() {
@@ -39,20 +53,24 @@ impl FileManager {
                 },
             };
 
-            max_index = std::cmp::max(max_index, index);
+            images.push((index, dir_entry.path()));
         }
 
-        Ok(FileManager {
-            write_location: location,
-            max_index
-        })
+        Ok(images)
+    }
+
+    /// Up to `count` of the images written last, newest first
+    pub fn recent_images(&self, count: usize) -> Result<Vec<PathBuf>> {
+        let mut images = Self::images(&self.write_location)?;
+        images.sort_by(|a, b| b.0.cmp(&a.0));
+        Ok(images.into_iter().take(count).map(|(_, path)| path).collect())
     }
 
     pub fn default() -> Result<FileManager> {
         Self::new(usb::StorageDevices::collect()
             .drives()
             .first()
-            .ok_or_else(|| anyhow!("No USB device connected"))?
+            .ok_or(BoothError::NoStorage)?
             .mount_point()
             .to_path_buf()
         )
@@ -64,6 +82,39 @@ impl FileManager {
         return path;
     }
 
+    /// Move an image into the hidden `.trash` folder next to it, so it can
+    /// still be recovered. Returns the new location
+    pub fn trash(&self, image: &Path) -> Result<PathBuf> {
+        let trash = self.write_location.join(".trash");
+        std::fs::create_dir_all(&trash).map_err(BoothError::writing)?;
+
+        let file_name = image.file_name().ok_or_else(|| anyhow!("{:?} is not a file", image))?;
+        let mut target = trash.join(file_name);
+        // Only the main folder is scanned for the next index, so names are
+        // reused after trashing the newest image and restarting
+        let mut copy = 1;
+        while target.exists() {
+            let stem = image.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = image.extension().unwrap_or_default().to_string_lossy();
+            target = trash.join(format!("{}-{}.{}", stem, copy, extension));
+            copy += 1;
+        }
+
+        std::fs::rename(image, &target).map_err(BoothError::writing)?;
+        info!("Moved {:?} to {:?}", image, target);
+        Ok(target)
+    }
+
+    /// The folder images are written to
+    pub fn location(&self) -> &Path {
+        &self.write_location
+    }
+
+    /// Thumbnails of the images written by this manager
+    pub fn thumbnails(&self) -> Thumbnails {
+        Thumbnails::new(&self.write_location)
+    }
+
     pub fn write_loc_exists(&self) -> bool {
         self.write_location.exists()
     }
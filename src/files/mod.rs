@@ -1,4 +1,6 @@
 mod manager;
+mod thumbnails;
 pub mod usb;
 
 pub use manager::*;
+pub use thumbnails::*;
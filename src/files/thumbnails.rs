@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use image::{ImageFormat, RgbaImage};
+use log::*;
+
+/// Thumbnails are scaled to fit in a square of this size
+pub const THUMBNAIL_SIZE: u32 = 400;
+
+/// Thumbnails of the images in a folder, stored in its hidden `.thumbnails`
+/// folder so images only have to be decoded in full once
+#[derive(Debug, Clone)]
+pub struct Thumbnails {
+    folder: PathBuf,
+}
+
+impl Thumbnails {
+    /// Thumbnails of the images in `location`
+    pub fn new(location: &Path) -> Self {
+        Thumbnails {
+            folder: location.join(".thumbnails"),
+        }
+    }
+
+    /// The thumbnail of `image`, created when it doesn't exist or is older
+    /// than the image
+    pub fn get(&self, image: &Path) -> Result<RgbaImage> {
+        let file_name = image.file_name().ok_or_else(|| anyhow!("{:?} is not a file", image))?;
+        // Keep the extension of the image, so IMG_1.png and IMG_1.jpg don't
+        // share a thumbnail
+        let mut thumbnail_name = file_name.to_owned();
+        thumbnail_name.push(".jpg");
+        let thumbnail_path = self.folder.join(thumbnail_name);
+
+        if is_up_to_date(&thumbnail_path, image) {
+            match ::image::open(&thumbnail_path) {
+                Ok(thumbnail) => return Ok(thumbnail.to_rgba8()),
+                Err(err) => warn!("Couldn't read thumbnail {:?}, creating it again: {}", thumbnail_path, err),
+            }
+        }
+
+        let thumbnail = ::image::open(image)?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+        // Not being able to cache the thumbnail (e.g. a write protected
+        // stick) only makes browsing slower
+        _ = std::fs::create_dir_all(&self.folder)
+            .and_then(|_| thumbnail.to_rgb8().save_with_format(&thumbnail_path, ImageFormat::Jpeg).map_err(std::io::Error::other))
+            .inspect_err(|err| warn!("Couldn't save thumbnail {:?}: {}", thumbnail_path, err));
+
+        Ok(thumbnail.to_rgba8())
+    }
+}
+
+/// Whether `thumbnail` exists and was written after `image` was last changed
+fn is_up_to_date(thumbnail: &Path, image: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(thumbnail), modified(image)) {
+        (Ok(thumbnail), Ok(image)) => thumbnail >= image,
+        _ => false,
+    }
+}
@@ -1,3 +1,6 @@
+use std::path::Path;
+
+use anyhow::Result;
 use ouroboros::self_referencing;
 use sysinfo::DiskRefreshKind;
 
@@ -23,4 +26,24 @@ impl StorageDevices {
     pub fn drives(&self) -> &Vec<& sysinfo::Disk> {
         self.borrow_mountable_disks()
     }
+
+    /// The drive called `name` (e.g. `sda1`)
+    pub fn drive(&self, name: &str) -> Option<&sysinfo::Disk> {
+        self.drives().iter().copied().find(|drive| drive.name() == name)
+    }
+
+    /// The drive `path` is on
+    pub fn drive_containing(&self, path: &Path) -> Option<&sysinfo::Disk> {
+        self.drives().iter().copied()
+            .filter(|drive| path.starts_with(drive.mount_point()))
+            .max_by_key(|drive| drive.mount_point().as_os_str().len())
+    }
+}
+
+/// Write all cached data and unmount the drive at `mount_point`, so it can be
+/// removed safely
+pub fn eject(mount_point: &Path) -> Result<()> {
+    unsafe { libc::sync() };
+    sys_mount::unmount(mount_point, sys_mount::UnmountFlags::empty())?;
+    Ok(())
 }
@@ -0,0 +1,272 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+
+use image::RgbaImage;
+use log::*;
+
+use crate::color::Color;
+use crate::files::Thumbnails;
+use crate::ui::{Image, ImageFit, UI, UIElement};
+
+/// Part of the screen height used by the thumbnail grid, the rest is left for
+/// the gallery layout
+const GRID_HEIGHT: f32 = 0.85;
+/// Space around each thumbnail, as part of the size of a grid cell
+const CELL_PADDING: f32 = 0.04;
+const PLACEHOLDER_COLOR: Color = Color::from_argb(0x30FFFFFF);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Request {
+    Thumbnail(PathBuf),
+    /// The image scaled to fit the screen
+    Full(PathBuf),
+}
+
+/// Decodes images on another thread, in the order they are requested.
+/// Requests made before the last `cancel` are skipped
+struct ImageLoader {
+    requests: Sender<(usize, Request)>,
+    results: Receiver<(Request, Option<RgbaImage>)>,
+    generation: Arc<AtomicUsize>,
+}
+
+impl ImageLoader {
+    fn new(thumbnails: Thumbnails, screen_size: (u32, u32)) -> Self {
+        let (request_sender, request_receiver) = std::sync::mpsc::channel::<(usize, Request)>();
+        let (result_sender, result_receiver) = std::sync::mpsc::channel();
+        let generation = Arc::new(AtomicUsize::new(0));
+
+        let current_generation = generation.clone();
+        // Stops when the loader is dropped and the request channel closes
+        std::thread::spawn(move || {
+            for (generation, request) in request_receiver {
+                if generation != current_generation.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let image = match &request {
+                    Request::Thumbnail(path) => thumbnails.get(path),
+                    Request::Full(path) => ::image::open(path)
+                        .map(|image| image.resize(screen_size.0, screen_size.1, ::image::imageops::FilterType::Triangle).to_rgba8())
+                        .map_err(Into::into),
+                };
+                let image = image.inspect_err(|err| {
+                    warn!("Couldn't load {:?}: {:?}", request, err);
+                }).ok();
+                if result_sender.send((request, image)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        ImageLoader {
+            requests: request_sender,
+            results: result_receiver,
+            generation,
+        }
+    }
+
+    fn request(&self, request: Request) {
+        _ = self.requests.send((self.generation.load(Ordering::Relaxed), request));
+    }
+
+    /// Skip all requests that weren't handled yet
+    fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Browses the photos in a folder, as pages of thumbnails or one photo at a
+/// time
+pub struct Gallery {
+    /// Newest first
+    images: Vec<PathBuf>,
+    columns: usize,
+    rows: usize,
+    page: usize,
+    /// Index of the image shown full screen
+    viewing: Option<usize>,
+    screen_size: (f32, f32),
+    loader: ImageLoader,
+    /// Decoded thumbnails of the current and nearby pages
+    thumbnails: HashMap<PathBuf, Rc<RgbaImage>>,
+    /// Elements on screen waiting for their image to be loaded
+    pending: HashMap<Request, Rc<RefCell<Image>>>,
+    /// Set when a thumbnail is touched
+    selected: Rc<Cell<Option<usize>>>,
+}
+
+impl Gallery {
+    pub fn new(images: Vec<PathBuf>, thumbnails: Thumbnails, columns: usize, rows: usize, screen_size: (usize, usize)) -> Self {
+        Gallery {
+            images,
+            columns: columns.max(1),
+            rows: rows.max(1),
+            page: 0,
+            viewing: None,
+            screen_size: (screen_size.0 as f32, screen_size.1 as f32),
+            loader: ImageLoader::new(thumbnails, (screen_size.0 as u32, screen_size.1 as u32)),
+            thumbnails: HashMap::new(),
+            pending: HashMap::new(),
+            selected: Rc::new(Cell::new(None)),
+        }
+    }
+
+    fn per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn pages(&self) -> usize {
+        self.images.len().div_ceil(self.per_page())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// The page or photo being shown, e.g. `2 / 5`
+    pub fn position(&self) -> String {
+        match self.viewing {
+            Some(index) => format!("{} / {}", index + 1, self.images.len()),
+            None => format!("{} / {}", self.page + 1, self.pages().max(1)),
+        }
+    }
+
+    /// Add the thumbnails of the current page, or the photo being viewed, to
+    /// `ui`. Images that aren't loaded yet are added when they are, see
+    /// `update`
+    pub fn build(&mut self, ui: &mut UI) {
+        self.loader.cancel();
+        self.pending.clear();
+        self.selected.set(None);
+
+        match self.viewing {
+            Some(index) => self.build_viewer(ui, index),
+            None => self.build_grid(ui),
+        }
+    }
+
+    fn build_viewer(&mut self, ui: &mut UI, index: usize) {
+        let path = self.images[index].clone();
+        // Show the thumbnail scaled up until the photo is loaded
+        let thumbnail = self.thumbnails.get(&path).cloned().unwrap_or_else(|| Rc::new(RgbaImage::new(0, 0)));
+        let image = ui.add_image((0., 0.), self.screen_size, &thumbnail, ImageFit::Contain);
+        self.pending.insert(Request::Full(path.clone()), image);
+        self.loader.request(Request::Full(path));
+    }
+
+    fn build_grid(&mut self, ui: &mut UI) {
+        let cell = (self.screen_size.0 / self.columns as f32, self.screen_size.1 * GRID_HEIGHT / self.rows as f32);
+        let padding = cell.0.min(cell.1) * CELL_PADDING;
+        let size = (cell.0 - 2. * padding, cell.1 - 2. * padding);
+
+        let first = self.page * self.per_page();
+        let last = (first + self.per_page()).min(self.images.len());
+        for index in first..last {
+            let cell_index = index - first;
+            let pos = (
+                (cell_index % self.columns) as f32 * cell.0 + padding,
+                (cell_index / self.columns) as f32 * cell.1 + padding,
+            );
+            ui.add_panel(pos, size, PLACEHOLDER_COLOR, padding);
+
+            let path = &self.images[index];
+            let image = match self.thumbnails.get(path) {
+                Some(thumbnail) => ui.add_image(pos, size, thumbnail, ImageFit::Contain),
+                None => {
+                    let image = ui.add_image(pos, size, &RgbaImage::new(0, 0), ImageFit::Contain);
+                    self.pending.insert(Request::Thumbnail(path.clone()), image.clone());
+                    self.loader.request(Request::Thumbnail(path.clone()));
+                    image
+                },
+            };
+
+            let selected = self.selected.clone();
+            image.borrow_mut().add_touch_listener(Box::new(move || selected.set(Some(index))));
+        }
+
+        // Load the next page in the background so paging doesn't wait for it
+        let next = (last + self.per_page()).min(self.images.len());
+        for path in &self.images[last..next] {
+            if !self.thumbnails.contains_key(path) {
+                self.loader.request(Request::Thumbnail(path.clone()));
+            }
+        }
+
+        self.forget_distant_thumbnails();
+    }
+
+    /// Keep the decoded thumbnails of the current and adjacent pages only
+    fn forget_distant_thumbnails(&mut self) {
+        let per_page = self.per_page();
+        if self.thumbnails.len() <= 4 * per_page {
+            return;
+        }
+        let first = self.page.saturating_sub(1) * per_page;
+        let last = ((self.page + 2) * per_page).min(self.images.len());
+        let keep = &self.images[first..last];
+        self.thumbnails.retain(|path, _| keep.contains(path));
+    }
+
+    /// Show loaded images. Returns true when a thumbnail was touched and the
+    /// screen should be built again
+    pub fn update(&mut self) -> bool {
+        while let Ok((request, image)) = self.loader.results.try_recv() {
+            let Some(image) = image else {
+                continue;
+            };
+            let image = Rc::new(image);
+            if let Some(element) = self.pending.remove(&request) {
+                element.borrow_mut().set_image(&image);
+            }
+            if let Request::Thumbnail(path) = request {
+                self.thumbnails.insert(path, image);
+            }
+        }
+
+        match self.selected.take() {
+            Some(index) => {
+                self.viewing = Some(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Go to the next page or photo. Returns true when the screen changed
+    pub fn next(&mut self) -> bool {
+        let pages = self.pages();
+        match &mut self.viewing {
+            Some(index) if *index + 1 < self.images.len() => *index += 1,
+            None if self.page + 1 < pages => self.page += 1,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Go to the previous page or photo. Returns true when the screen changed
+    pub fn previous(&mut self) -> bool {
+        match &mut self.viewing {
+            Some(index) if *index > 0 => *index -= 1,
+            None if self.page > 0 => self.page -= 1,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Close the photo being viewed, showing the page it is on. Returns false
+    /// when the grid was shown already
+    pub fn back(&mut self) -> bool {
+        match self.viewing.take() {
+            Some(index) => {
+                self.page = index / self.per_page();
+                true
+            },
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+/// Affine transformation correcting touch positions, in coordinates
+/// normalized to the (rotated) screen size:
+///
+/// ```text
+/// x' = m[0] * x + m[1] * y + m[2]
+/// y' = m[3] * x + m[4] * y + m[5]
+/// ```
+///
+/// The same layout as libinput's calibration matrix
+pub type CalibrationMatrix = [f64; 6];
+
+pub fn apply_calibration(matrix: &CalibrationMatrix, pos: (f64, f64)) -> (f64, f64) {
+    (
+        matrix[0] * pos.0 + matrix[1] * pos.1 + matrix[2],
+        matrix[3] * pos.0 + matrix[4] * pos.1 + matrix[5],
+    )
+}
+
+/// Targets are placed this fraction of the screen size away from the edges
+const TARGET_INSET: f64 = 0.1;
+
+/// Collects the touch positions of targets in the four corners of the screen
+/// and computes the calibration matrix from them
+pub struct CalibrationRoutine {
+    screen_size: (f64, f64),
+    /// Expected and measured positions, normalized
+    points: Vec<((f64, f64), (f64, f64))>,
+}
+
+impl CalibrationRoutine {
+    const TARGETS: [(f64, f64); 4] = [
+        (TARGET_INSET, TARGET_INSET),
+        (1. - TARGET_INSET, TARGET_INSET),
+        (1. - TARGET_INSET, 1. - TARGET_INSET),
+        (TARGET_INSET, 1. - TARGET_INSET),
+    ];
+
+    pub fn new(screen_size: (f64, f64)) -> Self {
+        CalibrationRoutine {
+            screen_size,
+            points: Vec::with_capacity(Self::TARGETS.len()),
+        }
+    }
+
+    /// The position of the target the user should touch next, in pixels
+    pub fn current_target(&self) -> Option<(f64, f64)> {
+        Self::TARGETS.get(self.points.len())
+            .map(|target| (target.0 * self.screen_size.0, target.1 * self.screen_size.1))
+    }
+
+    /// Record the uncalibrated position where the current target was touched
+    pub fn add_touch(&mut self, pos: (f64, f64)) {
+        if let Some(target) = Self::TARGETS.get(self.points.len()) {
+            self.points.push((*target, (pos.0 / self.screen_size.0, pos.1 / self.screen_size.1)));
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.points.len() == Self::TARGETS.len()
+    }
+
+    /// The least squares fit of the matrix mapping the measured positions to
+    /// the targets. `None` when the touches don't span an area (e.g. the same
+    /// spot was touched every time)
+    pub fn matrix(&self) -> Option<CalibrationMatrix> {
+        // Normal equations: (A^T A) m = A^T b with rows of A being [x y 1]
+        let mut ata = [[0.; 3]; 3];
+        let mut atb_x = [0.; 3];
+        let mut atb_y = [0.; 3];
+        for (target, measured) in &self.points {
+            let row = [measured.0, measured.1, 1.];
+            for i in 0..3 {
+                for j in 0..3 {
+                    ata[i][j] += row[i] * row[j];
+                }
+                atb_x[i] += row[i] * target.0;
+                atb_y[i] += row[i] * target.1;
+            }
+        }
+
+        let x = solve3(ata, atb_x)?;
+        let y = solve3(ata, atb_y)?;
+        Some([x[0], x[1], x[2], y[0], y[1], y[2]])
+    }
+}
+
+fn det3(m: &[[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+/// Solve `m x = b` using Cramer's rule
+fn solve3(m: [[f64; 3]; 3], b: [f64; 3]) -> Option<[f64; 3]> {
+    let det = det3(&m);
+    if det.abs() < 1e-9 {
+        return None;
+    }
+
+    let mut x = [0.; 3];
+    for (col, x) in x.iter_mut().enumerate() {
+        let mut mi = m;
+        for row in 0..3 {
+            mi[row][col] = b[row];
+        }
+        *x = det3(&mi) / det;
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: (f64, f64) = (800., 480.);
+
+    fn assert_close(a: (f64, f64), b: (f64, f64)) {
+        assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    /// Touch every target where `distort` moves it to, in pixels
+    fn calibrate(distort: impl Fn((f64, f64)) -> (f64, f64)) -> CalibrationRoutine {
+        let mut routine = CalibrationRoutine::new(SCREEN);
+        while let Some(target) = routine.current_target() {
+            routine.add_touch(distort(target));
+        }
+        routine
+    }
+
+    #[test]
+    fn accurate_touches_give_the_identity() {
+        let routine = calibrate(|pos| pos);
+        assert!(routine.is_done());
+        let matrix = routine.matrix().unwrap();
+        for (value, expected) in matrix.iter().zip([1., 0., 0., 0., 1., 0.]) {
+            assert!((value - expected).abs() < 1e-9, "{:?}", matrix);
+        }
+    }
+
+    #[test]
+    fn corrects_an_affine_distortion() {
+        // Scaled, sheared and offset, as a misaligned touch panel would be
+        let distort = |(x, y): (f64, f64)| (0.9 * x + 0.05 * y + 20., -0.02 * x + 1.1 * y - 15.);
+        let matrix = calibrate(distort).matrix().unwrap();
+
+        for pos in [(0., 0.), (400., 240.), (800., 480.), (123., 456.)] {
+            let touched = distort(pos);
+            let corrected = apply_calibration(&matrix, (touched.0 / SCREEN.0, touched.1 / SCREEN.1));
+            assert_close((corrected.0 * SCREEN.0, corrected.1 * SCREEN.1), pos);
+        }
+    }
+
+    #[test]
+    fn touches_on_one_spot_give_no_matrix() {
+        assert_eq!(calibrate(|_| (400., 240.)).matrix(), None);
+    }
+
+    #[test]
+    fn touches_on_a_line_give_no_matrix() {
+        assert_eq!(calibrate(|(x, _)| (x, 240.)).matrix(), None);
+    }
+
+    #[test]
+    fn extra_touches_are_ignored() {
+        let mut routine = calibrate(|pos| pos);
+        routine.add_touch((0., 0.));
+        assert!(routine.is_done());
+        assert_eq!(routine.current_target(), None);
+    }
+}
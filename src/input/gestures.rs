@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use log::*;
+
+use super::InputEvent;
+
+/// Maximum distance a finger can move for a touch to still count as a tap or
+/// long press
+const TAP_MAX_DISTANCE: f64 = 20.;
+const LONG_PRESS_TIME: Duration = Duration::from_millis(800);
+/// Maximum time between two taps of a double tap
+const DOUBLE_TAP_INTERVAL: Duration = Duration::from_millis(300);
+const SWIPE_MIN_DISTANCE: f64 = 80.;
+/// Minimum speed in pixels per second for a movement to count as a swipe
+const SWIPE_MIN_VELOCITY: f64 = 200.;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A finger on the screen
+#[derive(Debug, Clone, Copy)]
+struct TouchPoint {
+    start: (f64, f64),
+    start_time: Instant,
+    last: (f64, f64),
+    /// The touch has been reported as a long press and shouldn't produce any
+    /// other events
+    long_pressed: bool,
+}
+
+impl TouchPoint {
+    fn distance(&self) -> f64 {
+        distance(self.start, self.last)
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+fn center(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    ((a.0 + b.0) / 2., (a.1 + b.1) / 2.)
+}
+
+/// Turns touch down, motion and up events of each slot into gestures
+#[derive(Default)]
+pub struct GestureRecognizer {
+    slots: HashMap<u32, TouchPoint>,
+    /// Distance between the two fingers when the pinch started
+    pinch_start_distance: Option<f64>,
+    /// More than one finger touched the screen since all fingers were last
+    /// lifted. Such touches don't produce taps or swipes
+    multi_touch: bool,
+    last_tap: Option<(Instant, (f64, f64))>,
+}
+
+impl GestureRecognizer {
+    pub fn down(&mut self, slot: u32, pos: (f64, f64), now: Instant) -> Vec<InputEvent> {
+        let mut events = vec![InputEvent::Press { x: pos.0, y: pos.1 }];
+        self.slots.insert(slot, TouchPoint {
+            start: pos,
+            start_time: now,
+            last: pos,
+            long_pressed: false,
+        });
+
+        if self.slots.len() > 1 {
+            if !self.multi_touch {
+                events.push(InputEvent::Release);
+            }
+            self.multi_touch = true;
+        }
+        if self.slots.len() == 2 {
+            self.pinch_start_distance = self.pinch_points().map(|(a, b)| distance(a, b));
+        }
+
+        events
+    }
+
+    pub fn motion(&mut self, slot: u32, pos: (f64, f64)) -> Vec<InputEvent> {
+        let Some(point) = self.slots.get_mut(&slot) else {
+            return Vec::new();
+        };
+        point.last = pos;
+
+        match (self.pinch_start_distance, self.pinch_points()) {
+            (Some(start_distance), Some((a, b))) if start_distance > 0. => {
+                let center = center(a, b);
+                vec![InputEvent::Pinch {
+                    scale: distance(a, b) / start_distance,
+                    x: center.0,
+                    y: center.1,
+                }]
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn up(&mut self, slot: u32, now: Instant) -> Vec<InputEvent> {
+        let Some(point) = self.slots.remove(&slot) else {
+            return Vec::new();
+        };
+
+        if self.slots.len() < 2 {
+            self.pinch_start_distance = None;
+        }
+
+        let mut events = Vec::new();
+        if self.multi_touch {
+            if self.slots.is_empty() {
+                self.multi_touch = false;
+            }
+            return events;
+        }
+
+        events.push(InputEvent::Release);
+
+        if point.long_pressed {
+            return events;
+        }
+
+        let elapsed = now - point.start_time;
+        let moved = point.distance();
+        if moved <= TAP_MAX_DISTANCE {
+            let (x, y) = point.start;
+            trace!("Tap ({}, {})", x, y);
+            events.push(InputEvent::Tap { x, y });
+
+            match self.last_tap {
+                Some((time, pos)) if now - time <= DOUBLE_TAP_INTERVAL && distance(pos, point.start) <= TAP_MAX_DISTANCE => {
+                    trace!("Double tap ({}, {})", x, y);
+                    events.push(InputEvent::DoubleTap { x, y });
+                    self.last_tap = None;
+                },
+                _ => self.last_tap = Some((now, point.start)),
+            }
+        } else if moved >= SWIPE_MIN_DISTANCE {
+            let velocity = moved / elapsed.as_secs_f64().max(0.001);
+            if velocity >= SWIPE_MIN_VELOCITY {
+                let (dx, dy) = (point.last.0 - point.start.0, point.last.1 - point.start.1);
+                let direction = if dx.abs() > dy.abs() {
+                    if dx > 0. { SwipeDirection::Right } else { SwipeDirection::Left }
+                } else if dy > 0. {
+                    SwipeDirection::Down
+                } else {
+                    SwipeDirection::Up
+                };
+                trace!("Swipe {:?} at {} px/s", direction, velocity);
+                events.push(InputEvent::Swipe { direction, velocity });
+            }
+        }
+
+        events
+    }
+
+    /// All fingers were lifted or the touch sequence was taken over by the
+    /// system
+    pub fn cancel(&mut self) -> Vec<InputEvent> {
+        let was_pressed = !self.slots.is_empty() && !self.multi_touch;
+        self.slots.clear();
+        self.pinch_start_distance = None;
+        self.multi_touch = false;
+        if was_pressed { vec![InputEvent::Release] } else { Vec::new() }
+    }
+
+    /// Check for gestures that are recognized by time passing rather than by
+    /// an event. Should be called regularly
+    pub fn poll(&mut self, now: Instant) -> Vec<InputEvent> {
+        if self.multi_touch {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+        for point in self.slots.values_mut() {
+            if !point.long_pressed && now - point.start_time >= LONG_PRESS_TIME && point.distance() <= TAP_MAX_DISTANCE {
+                point.long_pressed = true;
+                let (x, y) = point.start;
+                trace!("Long press ({}, {})", x, y);
+                events.push(InputEvent::LongPress { x, y });
+            }
+        }
+        events
+    }
+
+    /// The positions of the two fingers of a pinch
+    fn pinch_points(&self) -> Option<((f64, f64), (f64, f64))> {
+        if self.slots.len() != 2 {
+            return None;
+        }
+        let mut points = self.slots.values();
+        Some((points.next()?.last, points.next()?.last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// A single finger touching `pos` at `start` and lifted after `held`
+    fn tap(gestures: &mut GestureRecognizer, pos: (f64, f64), start: Instant, held: Duration) -> Vec<InputEvent> {
+        gestures.down(0, pos, start);
+        gestures.up(0, start + held)
+    }
+
+    #[test]
+    fn short_touch_is_a_tap() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        assert_eq!(gestures.down(0, (10., 20.), start), [InputEvent::Press { x: 10., y: 20. }]);
+        assert!(gestures.poll(start + ms(100)).is_empty());
+        assert_eq!(gestures.up(0, start + ms(100)), [InputEvent::Release, InputEvent::Tap { x: 10., y: 20. }]);
+    }
+
+    #[test]
+    fn held_touch_is_a_long_press_and_not_a_tap() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        gestures.down(0, (10., 20.), start);
+        assert!(gestures.poll(start + LONG_PRESS_TIME - ms(1)).is_empty());
+        assert_eq!(gestures.poll(start + LONG_PRESS_TIME), [InputEvent::LongPress { x: 10., y: 20. }]);
+        // Reported once
+        assert!(gestures.poll(start + LONG_PRESS_TIME + ms(100)).is_empty());
+        assert_eq!(gestures.up(0, start + ms(1000)), [InputEvent::Release]);
+    }
+
+    #[test]
+    fn moving_finger_is_no_long_press() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        gestures.down(0, (10., 20.), start);
+        gestures.motion(0, (10. + TAP_MAX_DISTANCE + 1., 20.));
+        assert!(gestures.poll(start + ms(1000)).is_empty());
+    }
+
+    #[test]
+    fn two_taps_within_the_interval_are_a_double_tap() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        tap(&mut gestures, (10., 20.), start, ms(50));
+        let second = tap(&mut gestures, (15., 20.), start + ms(50) + DOUBLE_TAP_INTERVAL - ms(60), ms(50));
+        assert_eq!(second, [InputEvent::Release, InputEvent::Tap { x: 15., y: 20. }, InputEvent::DoubleTap { x: 15., y: 20. }]);
+
+        // A third tap starts over
+        let third = tap(&mut gestures, (15., 20.), start + ms(400), ms(50));
+        assert!(!third.iter().any(|event| matches!(event, InputEvent::DoubleTap { .. })));
+    }
+
+    #[test]
+    fn taps_too_far_apart_are_no_double_tap() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        tap(&mut gestures, (10., 20.), start, ms(50));
+        let late = tap(&mut gestures, (10., 20.), start + ms(50) + DOUBLE_TAP_INTERVAL + ms(1), ms(50));
+        assert_eq!(late, [InputEvent::Release, InputEvent::Tap { x: 10., y: 20. }]);
+
+        let elsewhere = tap(&mut gestures, (200., 20.), start + ms(500), ms(50));
+        assert_eq!(elsewhere, [InputEvent::Release, InputEvent::Tap { x: 200., y: 20. }]);
+    }
+
+    #[test]
+    fn fast_drag_is_a_swipe() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        gestures.down(0, (300., 100.), start);
+        gestures.motion(0, (150., 110.));
+        let events = gestures.up(0, start + ms(100));
+        let velocity = distance((300., 100.), (150., 110.)) / 0.1;
+        assert_eq!(events, [InputEvent::Release, InputEvent::Swipe { direction: SwipeDirection::Left, velocity }]);
+    }
+
+    #[test]
+    fn slow_drag_is_no_swipe() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        gestures.down(0, (100., 100.), start);
+        gestures.motion(0, (100., 250.));
+        // 150 px in a second
+        assert_eq!(gestures.up(0, start + ms(1000)), [InputEvent::Release]);
+    }
+
+    #[test]
+    fn short_drag_is_no_swipe() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        gestures.down(0, (100., 100.), start);
+        gestures.motion(0, (100. + SWIPE_MIN_DISTANCE - 1., 100.));
+        assert_eq!(gestures.up(0, start + ms(10)), [InputEvent::Release]);
+    }
+
+    #[test]
+    fn two_fingers_pinch() {
+        let mut gestures = GestureRecognizer::default();
+        let start = Instant::now();
+        gestures.down(0, (100., 100.), start);
+        // The second finger releases the first press
+        assert_eq!(gestures.down(1, (200., 100.), start + ms(10)), [InputEvent::Press { x: 200., y: 100. }, InputEvent::Release]);
+
+        assert_eq!(gestures.motion(1, (300., 100.)), [InputEvent::Pinch { scale: 2., x: 200., y: 100. }]);
+        assert_eq!(gestures.motion(0, (250., 100.)), [InputEvent::Pinch { scale: 0.5, x: 275., y: 100. }]);
+
+        // No long press, tap or swipe while or after pinching
+        assert!(gestures.poll(start + ms(1000)).is_empty());
+        assert!(gestures.up(1, start + ms(1000)).is_empty());
+        assert!(gestures.motion(0, (100., 100.)).is_empty());
+        assert!(gestures.up(0, start + ms(1010)).is_empty());
+
+        // Once all fingers were lifted, single touches work again
+        let events = tap(&mut gestures, (50., 50.), start + ms(2000), ms(50));
+        assert_eq!(events, [InputEvent::Release, InputEvent::Tap { x: 50., y: 50. }]);
+    }
+
+    #[test]
+    fn cancel_releases_the_press() {
+        let mut gestures = GestureRecognizer::default();
+        gestures.down(0, (10., 20.), Instant::now());
+        assert_eq!(gestures.cancel(), [InputEvent::Release]);
+        assert!(gestures.up(0, Instant::now()).is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+//! Linux key codes (linux/input-event-codes.h) by name, for key bindings in
+//! the configuration
+
+/// Names are matched case insensitively and the `KEY_` prefix is optional, so
+/// `KEY_SPACE`, `space` and `Space` are all the same key
+const KEY_CODES: &[(&str, u32)] = &[
+    ("ESC", 1),
+    ("1", 2), ("2", 3), ("3", 4), ("4", 5), ("5", 6), ("6", 7), ("7", 8), ("8", 9), ("9", 10), ("0", 11),
+    ("MINUS", 12), ("EQUAL", 13), ("BACKSPACE", 14), ("TAB", 15),
+    ("Q", 16), ("W", 17), ("E", 18), ("R", 19), ("T", 20), ("Y", 21), ("U", 22), ("I", 23), ("O", 24), ("P", 25),
+    ("LEFTBRACE", 26), ("RIGHTBRACE", 27), ("ENTER", 28), ("LEFTCTRL", 29),
+    ("A", 30), ("S", 31), ("D", 32), ("F", 33), ("G", 34), ("H", 35), ("J", 36), ("K", 37), ("L", 38),
+    ("SEMICOLON", 39), ("APOSTROPHE", 40), ("GRAVE", 41), ("LEFTSHIFT", 42), ("BACKSLASH", 43),
+    ("Z", 44), ("X", 45), ("C", 46), ("V", 47), ("B", 48), ("N", 49), ("M", 50),
+    ("COMMA", 51), ("DOT", 52), ("SLASH", 53), ("RIGHTSHIFT", 54), ("LEFTALT", 56), ("SPACE", 57),
+    ("F1", 59), ("F2", 60), ("F3", 61), ("F4", 62), ("F5", 63), ("F6", 64), ("F7", 65), ("F8", 66), ("F9", 67), ("F10", 68),
+    ("F11", 87), ("F12", 88), ("KPENTER", 96), ("RIGHTCTRL", 97), ("RIGHTALT", 100),
+    ("HOME", 102), ("UP", 103), ("PAGEUP", 104), ("LEFT", 105), ("RIGHT", 106), ("END", 107),
+    ("DOWN", 108), ("PAGEDOWN", 109), ("INSERT", 110), ("DELETE", 111),
+    ("MUTE", 113), ("VOLUMEDOWN", 114), ("VOLUMEUP", 115), ("POWER", 116), ("PAUSE", 119),
+    ("NEXTSONG", 163), ("PLAYPAUSE", 164), ("PREVIOUSSONG", 165), ("CAMERA", 212), ("PRINT", 210),
+    // Mouse buttons, so they can be bound like keys
+    ("BTN_LEFT", 0x110), ("BTN_RIGHT", 0x111), ("BTN_MIDDLE", 0x112),
+];
+
+pub const BTN_LEFT: u32 = 0x110;
+
+/// Look up the key code of a key name, or parse a numeric key code. Names
+/// come first, so `1` is the key 1 rather than code 1 (ESC)
+pub fn key_code(name: &str) -> Option<u32> {
+    let upper = name.to_uppercase();
+    let key_name = upper.strip_prefix("KEY_").unwrap_or(&upper);
+    KEY_CODES.iter()
+        .find(|(known, _)| *known == key_name)
+        .map(|(_, code)| *code)
+        .or_else(|| name.parse::<u32>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_are_keys() {
+        assert_eq!(key_code("1"), Some(2));
+        assert_eq!(key_code("0"), Some(11));
+        assert_eq!(key_code("KEY_1"), Some(2));
+    }
+
+    #[test]
+    fn names_ignore_case_and_prefix() {
+        assert_eq!(key_code("KEY_A"), Some(30));
+        assert_eq!(key_code("a"), Some(30));
+        assert_eq!(key_code("Key_Space"), Some(57));
+        assert_eq!(key_code("btn_left"), Some(BTN_LEFT));
+    }
+
+    #[test]
+    fn other_numbers_are_codes() {
+        assert_eq!(key_code("30"), Some(30));
+        assert_eq!(key_code("352"), Some(352));
+        assert_eq!(key_code("nokey"), None);
+    }
+}
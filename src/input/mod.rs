@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use input::event::keyboard::{KeyState, KeyboardEventTrait};
+use input::event::pointer::{Axis, ButtonState, PointerScrollEvent};
+use input::event::touch::{TouchEventPosition, TouchEventSlot};
+use input::event::{DeviceEvent, EventTrait, KeyboardEvent, PointerEvent, TouchEvent};
+use input::{Device, DeviceCapability, Event, Libinput, LibinputInterface};
+use libc::{O_RDONLY, O_RDWR, O_WRONLY};
+use log::*;
+
+use crate::display::Rotation;
+use crate::error::BoothError;
+
+pub mod keys;
+mod gestures;
+mod calibration;
+
+pub use gestures::SwipeDirection;
+pub use calibration::*;
+use gestures::GestureRecognizer;
+
+struct Interface;
+
+impl LibinputInterface for Interface {
+    fn open_restricted(&mut self, path: &Path, flags: i32) -> std::result::Result<OwnedFd, i32> {
+        OpenOptions::new()
+            .custom_flags(flags)
+            .read((flags & O_RDONLY != 0) | (flags & O_RDWR != 0))
+            .write((flags & O_WRONLY != 0) | (flags & O_RDWR != 0))
+            .open(path)
+            .map(|file| file.into())
+            .map_err(|err| err.raw_os_error().unwrap())
+    }
+    fn close_restricted(&mut self, fd: OwnedFd) {
+        drop(File::from(fd))
+    }
+}
+
+/// The seat used to discover devices with udev
+const SEAT: &str = "seat0";
+
+/// Which devices to listen to and how to map their coordinates
+#[derive(Debug, Clone)]
+pub struct InputOptions {
+    /// Only use the device at this path. All touch devices on the seat are
+    /// used when not set
+    pub device_path: Option<String>,
+    /// Only use touch devices whose name contains this string
+    pub device_name_filter: Option<String>,
+    /// Actions triggered by keys and buttons, by key code
+    pub key_bindings: HashMap<u32, InputAction>,
+    /// Width of the panel in its native orientation
+    pub screen_width: u32,
+    /// Height of the panel in its native orientation
+    pub screen_height: u32,
+    /// Touch events are reported in the coordinate space of the screen rotated
+    /// by `rotation`
+    pub rotation: Rotation,
+    /// Correction applied to touch positions, after rotation
+    pub calibration: Option<CalibrationMatrix>,
+}
+
+impl InputOptions {
+    /// Whether touch events of `device` should be handled
+    fn accepts_touch(&self, device: &Device) -> bool {
+        device.has_capability(DeviceCapability::Touch)
+            && self.device_name_filter.as_ref().map_or(true, |filter| device.name().contains(filter.as_str()))
+    }
+
+    /// What `device` is used for
+    fn role(&self, device: &Device) -> DeviceRole {
+        self.role_of(
+            self.accepts_touch(device),
+            device.has_capability(DeviceCapability::Keyboard) || device.has_capability(DeviceCapability::Pointer),
+            |code| device.keyboard_has_key(code) == Ok(true) || device.pointer_has_button(code) == Ok(true),
+        )
+    }
+
+    /// `role` of a device that is an accepted touch screen when `touch`, a
+    /// keyboard or pointer when `has_keys`, and has the keys and buttons for
+    /// which `has_key` is true
+    fn role_of(&self, touch: bool, has_keys: bool, has_key: impl Fn(u32) -> bool) -> DeviceRole {
+        if touch {
+            DeviceRole::Touch
+        } else if !has_keys {
+            DeviceRole::Ignored
+        } else if has_key(keys::BTN_LEFT) || self.key_bindings.keys().any(|code| has_key(*code)) {
+            DeviceRole::Control
+        } else {
+            DeviceRole::Other
+        }
+    }
+}
+
+/// What an input device is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeviceRole {
+    /// A touch screen
+    Touch,
+    /// A keyboard, button or mouse that can operate the booth without a
+    /// touch screen: it has a bound key, or a left button to click with
+    Control,
+    /// Its events are handled, but it can't operate the booth on its own
+    Other,
+    Ignored,
+}
+
+impl DeviceRole {
+    /// Whether the booth can be used with the device, see `InputHealth`
+    fn is_usable(self) -> bool {
+        matches!(self, DeviceRole::Touch | DeviceRole::Control)
+    }
+}
+
+/// What a key or button can be bound to in the configuration
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InputAction {
+    StartCapture,
+    /// Keep the photo being reviewed
+    Keep,
+    /// Discard the photo being reviewed and take another one
+    Retake,
+    /// Discard the photo being reviewed
+    Delete,
+    /// Browse the photos taken so far
+    OpenGallery,
+    /// The next page or photo in the gallery
+    Next,
+    /// The previous page or photo in the gallery
+    Previous,
+    /// Close the photo or the gallery
+    Back,
+    /// Switch the texts to the next of the configured languages
+    NextLanguage,
+    OpenAdminMenu,
+    /// Start the touch screen calibration
+    Calibrate,
+}
+
+/// Positions are on the (rotated) screen
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputEvent {
+    /// A single finger touched the screen
+    Press { x: f64, y: f64 },
+    /// The finger of the last `Press` was lifted, or a second finger touched
+    /// the screen
+    Release,
+    /// A short touch or mouse click
+    Tap { x: f64, y: f64 },
+    /// A second tap at the same position shortly after the first one. Both
+    /// taps are reported as well
+    DoubleTap { x: f64, y: f64 },
+    /// A touch held in place
+    LongPress { x: f64, y: f64 },
+    /// A fast movement of a single finger. `velocity` is in pixels per second
+    Swipe { direction: SwipeDirection, velocity: f64 },
+    /// Two fingers moving apart (`scale` > 1) or together (`scale` < 1),
+    /// relative to when the second finger touched the screen
+    Pinch { scale: f64, x: f64, y: f64 },
+    /// A key or button was pressed
+    Key { code: u32, action: Option<InputAction> },
+    /// Scroll wheel movement in wheel clicks. Positive values scroll down or
+    /// to the right
+    Scroll { dx: f64, dy: f64 },
+}
+
+/// State of the input subsystem
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputHealth {
+    /// At least one touch screen, bound key or button, or mouse is connected
+    Ok,
+    /// No device the booth can be operated with is connected
+    NoDevice,
+    /// The input context couldn't be created or failed. It is recreated
+    /// automatically
+    Failed(String),
+}
+
+impl InputHealth {
+    pub fn is_ok(&self) -> bool {
+        *self == InputHealth::Ok
+    }
+
+    /// The error shown to guests, `None` when input works
+    pub fn error(&self) -> Option<BoothError> {
+        match self {
+            InputHealth::Ok => None,
+            InputHealth::NoDevice | InputHealth::Failed(_) => Some(BoothError::NoTouchDevice),
+        }
+    }
+}
+
+/// Time between attempts to recover from a failed input context
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// How long `InputManager::new` waits for the devices that are already
+/// connected to be found
+const STARTUP_SCAN_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Subscribers = Arc<RwLock<Vec<Sender<InputEvent>>>>;
+
+pub struct InputManager {
+    dispatch_thread: Option<JoinHandle<()>>,
+    stop_thread: Sender<()>,
+    touch_subscribers: Subscribers,
+    health: Arc<RwLock<InputHealth>>,
+    calibration: Arc<RwLock<Option<CalibrationMatrix>>>,
+}
+
+impl InputManager {
+    pub fn new(options: InputOptions) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let subscribers = Arc::new(RwLock::new(Vec::<Sender<InputEvent>>::new()));
+        let health = Arc::new(RwLock::new(InputHealth::NoDevice));
+        let calibration = Arc::new(RwLock::new(options.calibration));
+
+        let pointer = options.rotation.logical_size((options.screen_width as f64 / 2., options.screen_height as f64 / 2.));
+        let mut dispatcher = Dispatcher {
+            options,
+            subscribers: subscribers.clone(),
+            health: health.clone(),
+            calibration: calibration.clone(),
+            usable_devices: 0,
+            path_device: None,
+            gestures: GestureRecognizer::default(),
+            pointer,
+        };
+        // Signalled once the devices connected at startup were added, or
+        // adding them failed
+        let (scanned_sender, scanned_receiver) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let mut scanned = Some(scanned_sender);
+            'context_loop: loop {
+                let mut input = match dispatcher.create_context() {
+                    Ok(input) => input,
+                    Err(err) => {
+                        error!("Couldn't create input context: {}", err);
+                        dispatcher.set_health(InputHealth::Failed(err));
+                        scanned.take().map(|scanned| scanned.send(()));
+                        if Self::should_stop(&rx, RETRY_INTERVAL) {
+                            break 'context_loop;
+                        }
+                        continue 'context_loop;
+                    },
+                };
+
+                loop {
+                    dispatcher.readd_removed_device(&mut input);
+
+                    if let Err(err) = input.dispatch() {
+                        error!("Input dispatch failed, recreating input context: {:?}", err);
+                        dispatcher.set_health(InputHealth::Failed(err.to_string()));
+                        scanned.take().map(|scanned| scanned.send(()));
+                        if Self::should_stop(&rx, RETRY_INTERVAL) {
+                            break 'context_loop;
+                        }
+                        continue 'context_loop;
+                    }
+
+                    for event in &mut input {
+                        dispatcher.handle_event(event);
+                    }
+                    dispatcher.poll_gestures();
+                    // The first dispatch adds the devices already connected
+                    scanned.take().map(|scanned| scanned.send(()));
+
+                    if Self::should_stop(&rx, Duration::from_millis(10)) {
+                        break 'context_loop;
+                    }
+                }
+            }
+        });
+
+        // Until then the health would report that there are no devices
+        if scanned_receiver.recv_timeout(STARTUP_SCAN_TIMEOUT).is_err() {
+            warn!("Input devices weren't found within {:?}", STARTUP_SCAN_TIMEOUT);
+        }
+
+        return InputManager {
+            dispatch_thread: Some(handle),
+            stop_thread: tx,
+            touch_subscribers: subscribers,
+            health,
+            calibration,
+        };
+    }
+
+    /// Wait for `timeout` and return whether the dispatch thread should stop
+    fn should_stop(rx: &Receiver<()>, timeout: Duration) -> bool {
+        match rx.recv_timeout(timeout) {
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => true,
+            Err(RecvTimeoutError::Timeout) => false,
+        }
+    }
+
+    // Subscribe to input events
+    pub fn subscribe(&self, receiver: Sender<InputEvent>) {
+        let mut guard = self.touch_subscribers.write().unwrap();
+        guard.push(receiver);
+    }
+
+    pub fn health(&self) -> InputHealth {
+        self.health.read().unwrap().clone()
+    }
+
+    /// Change the correction applied to touch positions. Use `None` to get
+    /// uncalibrated positions (e.g. while calibrating)
+    pub fn set_calibration(&self, calibration: Option<CalibrationMatrix>) {
+        *self.calibration.write().unwrap() = calibration;
+    }
+}
+
+impl Drop for InputManager {
+    fn drop(&mut self) {
+        _ = self.stop_thread.send(());
+        if let Some(handle) = self.dispatch_thread.take() {
+            if handle.join().is_err() {
+                error!("Input dispatch thread panicked");
+            }
+        }
+    }
+}
+
+/// State of the dispatch thread
+struct Dispatcher {
+    options: InputOptions,
+    subscribers: Subscribers,
+    health: Arc<RwLock<InputHealth>>,
+    calibration: Arc<RwLock<Option<CalibrationMatrix>>>,
+    /// Number of connected devices the booth can be operated with, see
+    /// `DeviceRole::is_usable`
+    usable_devices: usize,
+    /// Sysname (e.g. `event3`) of the device at `options.device_path`, while
+    /// it is part of the context
+    path_device: Option<String>,
+    gestures: GestureRecognizer,
+    /// Position of the mouse pointer on the (rotated) screen
+    pointer: (f64, f64),
+}
+
+impl Dispatcher {
+    fn create_context(&mut self) -> Result<Libinput, String> {
+        self.usable_devices = 0;
+        self.path_device = None;
+        match &self.options.device_path {
+            Some(path) => {
+                let mut input = Libinput::new_from_path(Interface);
+                self.path_device = input.path_add_device(path).map(|device| device.sysname().to_string());
+                if self.path_device.is_none() {
+                    warn!("Couldn't add input device {}, waiting for it to appear", path);
+                    self.set_health(InputHealth::NoDevice);
+                }
+                Ok(input)
+            },
+            None => {
+                let mut input = Libinput::new_with_udev(Interface);
+                input.udev_assign_seat(SEAT).map_err(|_| format!("Couldn't assign seat {}", SEAT))?;
+                Ok(input)
+            },
+        }
+    }
+
+    /// The path backend doesn't pick up devices that are plugged back in, so
+    /// add the configured device again when it is gone
+    fn readd_removed_device(&mut self, input: &mut Libinput) {
+        let Some(path) = &self.options.device_path else {
+            return;
+        };
+        if self.path_device.is_none() && Path::new(path).exists() {
+            self.path_device = input.path_add_device(path).map(|device| device.sysname().to_string());
+        }
+    }
+
+    fn set_health(&self, health: InputHealth) {
+        let mut guard = self.health.write().unwrap();
+        if *guard != health {
+            info!("Input health: {:?}", health);
+            *guard = health;
+        }
+    }
+
+    fn update_device_health(&self) {
+        self.set_health(if self.usable_devices > 0 { InputHealth::Ok } else { InputHealth::NoDevice });
+    }
+
+    /// Send an event to all subscribers, dropping those that went away
+    fn publish(&self, event: InputEvent) {
+        let mut subscribers = self.subscribers.write().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(event).is_ok());
+    }
+
+    fn publish_all(&self, events: Vec<InputEvent>) {
+        for event in events {
+            self.publish(event);
+        }
+    }
+
+    fn poll_gestures(&mut self) {
+        let events = self.gestures.poll(Instant::now());
+        self.publish_all(events);
+    }
+
+    /// Position of a touch on the (rotated) screen
+    fn touch_position(&self, event: &impl TouchEventPosition) -> (f64, f64) {
+        let (screen_width, screen_height) = (self.options.screen_width, self.options.screen_height);
+        let pos = self.options.rotation.physical_to_logical(
+            (event.x_transformed(screen_width), event.y_transformed(screen_height)),
+            (screen_width as f64, screen_height as f64)
+        );
+
+        let Some(matrix) = *self.calibration.read().unwrap() else {
+            return pos;
+        };
+        let size = self.options.rotation.logical_size((screen_width as f64, screen_height as f64));
+        let (x, y) = apply_calibration(&matrix, (pos.0 / size.0, pos.1 / size.1));
+        (x * size.0, y * size.1)
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Device(DeviceEvent::Added(event)) => {
+                let device = event.device();
+                let role = self.options.role(&device);
+                if role == DeviceRole::Ignored {
+                    debug!("Ignoring input device {} ({})", device.name(), device.sysname());
+                } else {
+                    info!("Input device added: {} ({}, {:?})", device.name(), device.sysname(), role);
+                    if role.is_usable() {
+                        self.usable_devices += 1;
+                        self.update_device_health();
+                    }
+                }
+            },
+            Event::Device(DeviceEvent::Removed(event)) => {
+                let device = event.device();
+                info!("Input device removed: {} ({})", device.name(), device.sysname());
+                if self.path_device.as_deref() == Some(device.sysname()) {
+                    self.path_device = None;
+                }
+                let role = self.options.role(&device);
+                if role == DeviceRole::Touch {
+                    let events = self.gestures.cancel();
+                    self.publish_all(events);
+                }
+                if role.is_usable() {
+                    self.usable_devices = self.usable_devices.saturating_sub(1);
+                    self.update_device_health();
+                }
+            },
+            Event::Touch(touch_event) if !self.options.accepts_touch(&touch_event.device()) => {},
+            Event::Touch(touch_event) => {
+                let now = Instant::now();
+                let events = match touch_event {
+                    TouchEvent::Down(down) => {
+                        let pos = self.touch_position(&down);
+                        trace!("Touch down {} {:?}", down.seat_slot(), pos);
+                        self.gestures.down(down.seat_slot(), pos, now)
+                    },
+                    TouchEvent::Motion(motion) => {
+                        let pos = self.touch_position(&motion);
+                        self.gestures.motion(motion.seat_slot(), pos)
+                    },
+                    TouchEvent::Up(up) => {
+                        trace!("Touch up {}", up.seat_slot());
+                        self.gestures.up(up.seat_slot(), now)
+                    },
+                    TouchEvent::Cancel(_) => {
+                        info!("Touch cancelled");
+                        self.gestures.cancel()
+                    },
+                    TouchEvent::Frame(_) => Vec::new(), // end of an event
+                    ev => {
+                        info!("Unhandled touch event {:#?}", ev);
+                        Vec::new()
+                    },
+                };
+                self.publish_all(events);
+            },
+            Event::Keyboard(KeyboardEvent::Key(key_event)) => {
+                if key_event.key_state() == KeyState::Pressed {
+                    self.key_pressed(key_event.key());
+                }
+            },
+            Event::Pointer(pointer_event) => self.handle_pointer_event(pointer_event),
+            _ => {}
+        }
+    }
+
+    fn key_pressed(&self, code: u32) {
+        let action = self.options.key_bindings.get(&code).copied();
+        trace!("Key {} pressed (action: {:?})", code, action);
+        self.publish(InputEvent::Key { code, action });
+    }
+
+    fn handle_pointer_event(&mut self, event: PointerEvent) {
+        let (screen_width, screen_height) = self.options.rotation.logical_size((self.options.screen_width as f64, self.options.screen_height as f64));
+
+        match event {
+            PointerEvent::Motion(motion) => {
+                self.pointer = (
+                    (self.pointer.0 + motion.dx()).clamp(0., screen_width),
+                    (self.pointer.1 + motion.dy()).clamp(0., screen_height),
+                );
+            },
+            PointerEvent::MotionAbsolute(motion) => {
+                self.pointer = self.options.rotation.physical_to_logical(
+                    (motion.absolute_x_transformed(self.options.screen_width), motion.absolute_y_transformed(self.options.screen_height)),
+                    (self.options.screen_width as f64, self.options.screen_height as f64)
+                );
+            },
+            PointerEvent::Button(button) => {
+                let code = button.button();
+                match button.button_state() {
+                    // Bound buttons act like keys, an unbound left click is a tap
+                    ButtonState::Pressed if self.options.key_bindings.contains_key(&code) => self.key_pressed(code),
+                    _ if code != keys::BTN_LEFT || self.options.key_bindings.contains_key(&code) => {},
+                    ButtonState::Pressed => {
+                        self.publish(InputEvent::Press { x: self.pointer.0, y: self.pointer.1 });
+                    },
+                    ButtonState::Released => {
+                        trace!("Click {:?}", self.pointer);
+                        self.publish(InputEvent::Release);
+                        self.publish(InputEvent::Tap { x: self.pointer.0, y: self.pointer.1 });
+                    },
+                }
+            },
+            PointerEvent::ScrollWheel(scroll) => {
+                let value = |axis| if scroll.has_axis(axis) { scroll.scroll_value_v120(axis) / 120. } else { 0. };
+                self.publish(InputEvent::Scroll { dx: value(Axis::Horizontal), dy: value(Axis::Vertical) });
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_SPACE: u32 = 57;
+    const KEY_A: u32 = 30;
+
+    fn options(key_bindings: impl IntoIterator<Item = (u32, InputAction)>) -> InputOptions {
+        InputOptions {
+            device_path: None,
+            device_name_filter: None,
+            key_bindings: key_bindings.into_iter().collect(),
+            screen_width: 800,
+            screen_height: 480,
+            rotation: Rotation::Rotate0,
+            calibration: None,
+        }
+    }
+
+    #[test]
+    fn keyboard_with_a_bound_key_operates_the_booth() {
+        let options = options([(KEY_SPACE, InputAction::StartCapture)]);
+        let role = options.role_of(false, true, |code| code == KEY_SPACE || code == KEY_A);
+        assert_eq!(role, DeviceRole::Control);
+        assert!(role.is_usable());
+    }
+
+    #[test]
+    fn keyboard_without_bound_keys_does_not() {
+        let options = options([(KEY_SPACE, InputAction::StartCapture)]);
+        let role = options.role_of(false, true, |code| code == KEY_A);
+        assert_eq!(role, DeviceRole::Other);
+        assert!(!role.is_usable());
+    }
+
+    #[test]
+    fn mouse_clicks_are_taps() {
+        let role = options([]).role_of(false, true, |code| code == keys::BTN_LEFT);
+        assert!(role.is_usable());
+    }
+
+    #[test]
+    fn touch_screens_and_other_devices() {
+        let options = options([]);
+        assert_eq!(options.role_of(true, false, |_| false), DeviceRole::Touch);
+        assert_eq!(options.role_of(false, false, |_| true), DeviceRole::Ignored);
+    }
+}
@@ -1,9 +1,14 @@
 #![feature(mpmc_channel)]
 
+pub mod backlight;
 pub mod camera;
 pub mod display;
 pub mod ui;
+pub mod color;
 pub mod config;
 pub mod input;
 pub mod files;
+pub mod gallery;
+pub mod admin;
+pub mod error;
 pub mod utils;
@@ -2,32 +2,66 @@
 
 use std::backtrace::BacktraceStatus;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, BufReader, BufWriter};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::process::Command;
 use std::rc::Rc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, Result};
-use chrono::{TimeDelta, Utc};
-use drm::buffer::DrmFourcc;
-use image::{ImageBuffer, ImageFormat};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use image::{ImageBuffer, RgbaImage};
 use log::*;
+use photobooth::admin::{AdminMenu, AdminRequest, AdminSettings, AdminStatus, PinAttempts};
+use photobooth::backlight::Backlight;
 use photobooth::camera::{Camera, CameraManager};
+use photobooth::color::Color;
+use photobooth::config::{ConfigProblem, ConfigWatcher, IdleAction, Texts};
 use photobooth::display::Display;
+use photobooth::error::BoothError;
 use photobooth::files::{self, FileManager};
-use photobooth::input::InputManager;
-use photobooth::ui::{TextBox, UIElement, UI};
+use photobooth::gallery::Gallery;
+use photobooth::input::{keys, CalibrationRoutine, InputAction, InputEvent, InputManager, InputOptions, SwipeDirection};
+use photobooth::ui::{Background, BackgroundImage, BuiltLayout, CrossFade, Easing, FontSet, ImageFit, LayoutElement, LayoutVariables, Property, TextEffects, Timeline, Tween, UI};
 use photobooth::utils::UnsafePtr;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Where log4rs writes the log, the end of it is shown in the admin menu
+const LOG_FILE: &str = "/var/log/photobooth.log";
+/// Lines of the log shown in the admin menu
+const ADMIN_LOG_LINES: usize = 8;
+/// How long to wait for input while the screen is off, before checking the
+/// configuration file and timers again
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Problems in the configuration shown on screen, the others are only logged
+const MAX_REPORTED_PROBLEMS: usize = 12;
+/// Part of the screen width and height in the top left corner that opens the
+/// admin menu when held
+const ADMIN_CORNER_SIZE: f64 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum AppState {
     TakePicturePrompt,
+    /// Recent photos shown while nobody uses the booth
+    Attract,
     /// Shows the countdown and previeuw
     TakingPicture,
     /// Capture
     TakePicture,
+    /// The photo just taken, to keep, retake or delete
+    Review,
+    /// Browse the photos taken so far
+    Gallery,
+    /// Settings and status for the operator, behind a PIN
+    Admin,
+
+    /// Touch screen calibration
+    Calibration,
+    /// Problems found in the configuration file, shown at startup
+    ConfigReport,
 
     Error
 }
@@ -37,88 +71,195 @@ impl AppState {
         return *self == AppState::TakingPicture;
     }
 
-    fn bg_color(&self, config: &photobooth::config::Config) -> u32 {
+    fn bg_color(&self, config: &photobooth::config::Config) -> Color {
         match self {
-            AppState::Error => config.error_bg_color,
+            AppState::Error | AppState::ConfigReport => config.error_bg_color,
             _ => config.bg_color
         }
     }
+
+    fn background_image<'a>(&self, config: &'a photobooth::config::Config) -> Option<&'a BackgroundImage> {
+        match self {
+            AppState::TakePicturePrompt => config.backgrounds.take_picture_prompt.as_ref(),
+            AppState::TakePicture => config.backgrounds.take_picture.as_ref(),
+            AppState::Error => config.backgrounds.error.as_ref(),
+            _ => None,
+        }
+    }
 }
 
 struct App<'a> {
     config: photobooth::config::Config,
+    /// The language shown, and the texts in it
+    language: String,
+    texts: Texts,
 
     disp: Display,
     ui: UI,
-    time_sensitive_ui: Vec<(Rc<RefCell<TextBox>>, TimeDelta, Box<dyn Fn(TimeDelta, TimeDelta, &Rc<RefCell<TextBox>>) -> ()>)>,
+    /// Fade from the previous screen, while switching screens. Kept between
+    /// transitions so the snapshot buffer is only allocated once
+    cross_fade: CrossFade,
+    /// Backgrounds of the screens not showing the camera preview
+    backgrounds: HashMap<AppState, Rc<RefCell<Background>>>,
 
     camera: Camera<'a>,
     camera_receiver: std::sync::mpmc::Receiver<libcamera::request::Request>,
 
     // must be kept alive to send input events to UI
-    #[allow(unused)]
     input: InputManager,
+    /// Receives every input event, used to detect when the booth is idle and
+    /// to handle key bindings
+    input_receiver: Receiver<InputEvent>,
+    last_activity: DateTime<Utc>,
+    backlight: Option<Backlight>,
+    /// The screen is dimmed or off and nothing is drawn, see
+    /// `sleep_until_touched`
+    sleeping: bool,
+    calibration: Option<CalibrationRoutine>,
 
     file_manager: Option<FileManager>,
+    /// The photo shown on the review screen, and the photo scaled to the
+    /// screen until the review screen is built
+    reviewed_picture: Option<PathBuf>,
+    review_image: Option<RgbaImage>,
+    gallery: Option<Gallery>,
+    admin: Option<AdminMenu>,
+    /// Wrong PINs entered in the admin menu, over all times it was opened
+    pin_attempts: PinAttempts,
+
+    /// Shown on the configuration report screen
+    config_problems: Vec<ConfigProblem>,
+    config_watcher: Option<ConfigWatcher>,
+    /// Set when changed settings need the display, camera or input to be set
+    /// up again, which is done at the next idle prompt
+    restart: bool,
+    /// The configuration before the first change that needs a restart, to
+    /// go back to when the changed one can't be set up
+    previous_config: Option<photobooth::config::Config>,
 
     state: AppState,
-    error_message: Option<String>,
+    /// The error shown on the error screen, `Unknown` when not set
+    error: Option<BoothError>,
     state_change_receiver: Receiver<AppState>,
     state_change_sender: Sender<AppState>,
+    /// Actions of touched layout elements
+    action_receiver: Receiver<InputAction>,
+    action_sender: Sender<InputAction>,
 }
 
 impl<'a> App<'a> {
     pub fn new(
         config: photobooth::config::Config,
+        config_problems: Vec<ConfigProblem>,
         cam_manager: &'a CameraManager
     ) -> Result<Self> {
-        let format_drm = DrmFourcc::Xrgb8888;
         let format_u32 = u32::from_le_bytes([b'X', b'R', b'2', b'4']);
 
         // Display
-        info!("Initializing DRM (display)");
-        let disp = Display::new("/dev/dri/by-path/platform-gpu-card", format_drm, 24, 32)?;
+        info!("Initializing display");
+        let disp = Display::open(config.display_backend, &config.drm_device, &config.fb_device, config.rotation)?;
 
-        info!("Initialized display {}x{}", disp.size().0, disp.size().1);
+        info!("Initialized display {}x{} ({:?})", disp.size().0, disp.size().1, disp.rotation());
 
         // Camera
         info!("Initializing camera");
-        let mut camera = Camera::new(&cam_manager, format_u32, config.screen_width, config.screen_height)?;
+        let preview_size = (
+            config.screen_width.unwrap_or(disp.size().0 as u32),
+            config.screen_height.unwrap_or(disp.size().1 as u32),
+        );
+        let mut camera = Camera::new(&cam_manager, format_u32, preview_size.0, preview_size.1)?;
 
         // camera.queue_video_requests()?;
         let camera_receiver = camera.on_request_receiver();
 
         // Ui
-        let (mut ui, touch_sender) = UI::new((disp.size().0 as usize, disp.size().1 as usize));
-        ui.set_text_color(config.text_color);
+        let (mut ui, touch_sender) = UI::new(
+            (disp.size().0 as usize, disp.size().1 as usize),
+            FontSet::load(&config.fonts, &config.emoji_fonts),
+        );
+        Self::apply_text_style(&mut ui, &config);
+        let backgrounds = Self::create_backgrounds(&config, (disp.size().0 as usize, disp.size().1 as usize));
 
         // Input
-        let input = InputManager::new(
-            "/dev/input/by-id/usb-QDtech_MPI7003-event-if00".to_string(), // TODO: parameter
-            disp.size().0 as u32, disp.size().1 as u32
-        );
+        let input = InputManager::new(InputOptions {
+            device_path: config.input_device.clone(),
+            device_name_filter: config.input_device_filter.clone(),
+            key_bindings: config.key_bindings.iter().filter_map(|(key, action)| {
+                match keys::key_code(key) {
+                    Some(code) => Some((code, *action)),
+                    None => {
+                        warn!("Unknown key '{}' in key bindings", key);
+                        None
+                    }
+                }
+            }).collect(),
+            screen_width: disp.physical_size().0 as u32,
+            screen_height: disp.physical_size().1 as u32,
+            rotation: disp.rotation(),
+            calibration: config.touch_calibration,
+        });
         input.subscribe(touch_sender);
+        let (input_sender, input_receiver) = std::sync::mpsc::channel();
+        input.subscribe(input_sender);
+
+        let backlight = if config.idle_timeout.is_some() {
+            Backlight::find(config.backlight.as_ref().map(std::path::Path::new)).unwrap_or_else(|err| {
+                warn!("Couldn't open backlight: {:?}", err);
+                None
+            })
+        } else {
+            None
+        };
 
         let (state_change_sender, state_change_receiver) = std::sync::mpsc::channel();
+        let (action_sender, action_receiver) = std::sync::mpsc::channel();
 
-        // TODO: show selection when multiple USB devices are present instead of just the first one
         let file_manager = Self::create_file_manager(&config)?;
 
-        let error_message = if file_manager.is_none() { Some(config.error_no_usb_device.clone()) } else { None };
+        let language = config.language.clone();
+        let texts = config.texts(&language)?;
+        let error = if file_manager.is_none() { Some(BoothError::NoStorage) } else { None };
+
+        let config_watcher = ConfigWatcher::new(&photobooth::config::config_path()).inspect_err(|err| {
+            warn!("Changes to the configuration file won't be applied until restarting: {:?}", err);
+        }).ok();
 
         return Ok(App {
             config,
+            language,
+            texts,
             disp,
             camera,
             camera_receiver,
             ui,
             input,
-            state: if file_manager.is_none() { AppState::Error } else { AppState::TakePicturePrompt },
-            time_sensitive_ui: Vec::new(),
+            input_receiver,
+            last_activity: Utc::now(),
+            backlight,
+            sleeping: false,
+            calibration: None,
+            state: match () {
+                _ if !config_problems.is_empty() => AppState::ConfigReport,
+                _ if file_manager.is_none() => AppState::Error,
+                _ => AppState::TakePicturePrompt,
+            },
+            config_problems,
+            config_watcher,
+            restart: false,
+            previous_config: None,
+            cross_fade: CrossFade::new(Vec::new(), Duration::ZERO),
+            backgrounds,
             state_change_receiver,
             state_change_sender,
+            action_receiver,
+            action_sender,
             file_manager,
-            error_message
+            reviewed_picture: None,
+            review_image: None,
+            gallery: None,
+            admin: None,
+            pin_attempts: PinAttempts::default(),
+            error
         });
     }
 
@@ -129,11 +270,14 @@ impl<'a> App<'a> {
             match self.run() {
                 Ok(_) => return,
                 Err(err) => {
-                    error!("{}", err);
+                    // The cause is logged, guests get the message configured
+                    // for the error
+                    let error = BoothError::of(&err);
+                    error!("{}: {:#}", error.code(), err);
                     if err.backtrace().status() == BacktraceStatus::Captured {
                         error!("{}", err.backtrace());
                     }
-                    self.error_message = Some(err.to_string());
+                    self.error = Some(error);
                     self.state = AppState::Error;
                 },
             }
@@ -148,31 +292,105 @@ impl<'a> App<'a> {
 
         let mut prev_time = Utc::now();
         loop {
+            if self.sleeping {
+                match self.input_receiver.recv_timeout(IDLE_POLL_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => {},
+                    // A disconnected sender means the input thread stopped,
+                    // in which case nothing would ever wake us up again
+                    Ok(_) | Err(RecvTimeoutError::Disconnected) => self.wake_up()?,
+                }
+            }
+
+            // Key bindings and power saving
+            while let Ok(event) = self.input_receiver.try_recv() {
+                self.last_activity = Utc::now();
+                match event {
+                    // Any touch or key ends attract mode and the
+                    // configuration report, without doing what it would do on
+                    // the prompt
+                    InputEvent::Release | InputEvent::Key { .. } if matches!(self.state, AppState::Attract | AppState::ConfigReport) => {
+                        self.state_change_sender.send(AppState::TakePicturePrompt)?;
+                    },
+                    InputEvent::Key { action: Some(action), .. } => self.handle_action(action)?,
+                    InputEvent::Tap { x, y } if self.state == AppState::Calibration => self.calibration_touch((x, y))?,
+                    _ => {},
+                }
+            }
+            while let Ok(action) = self.action_receiver.try_recv() {
+                self.handle_action(action)?;
+            }
+            if self.config_watcher.as_mut().is_some_and(|watcher| watcher.changed()) {
+                self.reload_config()?;
+            }
+            let idle = matches!(self.state, AppState::TakePicturePrompt | AppState::Attract);
+            if self.restart && idle {
+                info!("Restarting to apply the changed configuration");
+                if self.sleeping {
+                    self.wake_up()?;
+                }
+                return Ok(());
+            }
+            if let Some(attract_timeout) = self.config.attract_timeout {
+                if self.state == AppState::TakePicturePrompt && Utc::now() - self.last_activity > TimeDelta::seconds(attract_timeout as i64) {
+                    self.state_change_sender.send(AppState::Attract)?;
+                    self.last_activity = Utc::now();
+                }
+            }
+            if self.state == AppState::Gallery && Utc::now() - self.last_activity > TimeDelta::seconds(self.config.gallery_timeout as i64) {
+                self.state_change_sender.send(AppState::TakePicturePrompt)?;
+                self.last_activity = Utc::now();
+            }
+            if let Some(idle_timeout) = self.config.idle_timeout {
+                if idle && !self.sleeping && Utc::now() - self.last_activity > TimeDelta::seconds(idle_timeout as i64) {
+                    self.sleep_until_touched();
+                }
+            }
+            if self.sleeping {
+                // Nothing is drawn, but screens are still switched so the
+                // right one is shown when waking up
+                if let Ok(new_state) = self.state_change_receiver.try_recv() {
+                    self.transition(Some(self.state), new_state)?;
+                }
+                prev_time = Utc::now();
+                continue;
+            }
+
             let new_time = Utc::now();
             let delta = new_time - prev_time;
-            // Show video stream or clear screen
+            // Show video stream, other screens draw their background with the
+            // UI
             let show_video_stream = self.state.show_video_stream();
             if show_video_stream {
-                req = Some(self.camera_receiver.recv_timeout(Duration::from_secs(2))?);
+                req = Some(self.camera_receiver.recv_timeout(Duration::from_secs(2)).context(BoothError::CameraLost)?);
 
                 let fb_ptr = self.camera.video_stream().get_mapped_buffer(req.as_ref().unwrap().cookie());
-                unsafe { self.disp.copy_dma_buf(fb_ptr, self.camera.video_stream().get_frame_size() as usize)? };
-            } else {
-                self.disp.clear(self.state.bg_color(&self.config))?;
+                let video_stream = self.camera.video_stream();
+                unsafe { self.disp.copy_dma_buf(fb_ptr, video_stream.get_frame_size() as usize, video_stream.frame_layout())? };
             }
 
             // Update UI
-            for (textbox, ui_delta, cb) in self.time_sensitive_ui.iter_mut() {
-                cb(*ui_delta, *ui_delta + delta, textbox);
-                *ui_delta = *ui_delta + delta;
-            }
+            let delta = delta.to_std().unwrap_or_default();
+            self.ui.animate(delta);
             self.ui.update();
+            if self.gallery.as_mut().is_some_and(|gallery| gallery.update()) {
+                self.build_gallery_ui();
+            }
+            if let Some(admin) = &mut self.admin {
+                let requests = admin.update(&mut self.ui);
+                if !requests.is_empty() {
+                    self.handle_admin_requests(requests)?;
+                }
+            }
 
             // Render UI
             {
                 let mut buffer = self.disp.back_buffer_mut()?;
                 let buffer = buffer.as_mut();
                 self.ui.render(buffer);
+                if !self.cross_fade.is_finished() {
+                    self.cross_fade.render(buffer);
+                    self.cross_fade.advance(delta);
+                }
             }
 
             // Update display
@@ -189,12 +407,347 @@ impl<'a> App<'a> {
             // Transition state
             if let Ok(new_state) = self.state_change_receiver.try_recv() {
                 self.transition(Some(self.state), new_state)?;
+            } else if self.state == AppState::TakePicturePrompt && !self.input.health().is_ok() {
+                self.transition(Some(self.state), AppState::TakePicturePrompt)?;
             }
 
             prev_time = new_time;
         }
     }
 
+    fn handle_action(&mut self, action: InputAction) -> Result<()> {
+        match (action, self.state) {
+            (InputAction::StartCapture, AppState::TakePicturePrompt) => {
+                self.state_change_sender.send(AppState::TakingPicture)?;
+            },
+            (InputAction::Calibrate, AppState::TakePicturePrompt) => {
+                self.state_change_sender.send(AppState::Calibration)?;
+            },
+            (InputAction::OpenAdminMenu, AppState::TakePicturePrompt) => {
+                match self.config.admin_pin {
+                    Some(_) if self.pin_attempts.is_locked(Instant::now()) => warn!("The admin menu is locked after too many wrong PINs"),
+                    Some(_) => self.state_change_sender.send(AppState::Admin)?,
+                    None => warn!("The admin menu is disabled, set adminPin to enable it"),
+                }
+            },
+            (InputAction::NextLanguage, AppState::TakePicturePrompt) => {
+                let languages = self.config.languages();
+                let next = languages.iter().position(|language| *language == self.language).map_or(0, |index| (index + 1) % languages.len());
+                self.set_language(languages[next].clone());
+                // Show the prompt in the new language
+                self.state_change_sender.send(AppState::TakePicturePrompt)?;
+            },
+            (InputAction::OpenGallery, AppState::TakePicturePrompt) => {
+                if self.file_manager.is_none() {
+                    self.file_manager = Self::create_file_manager(&self.config)?;
+                }
+                match self.file_manager {
+                    Some(_) => self.state_change_sender.send(AppState::Gallery)?,
+                    None => warn!("No storage device to show photos from"),
+                }
+            },
+            (InputAction::Next | InputAction::Previous | InputAction::Back, AppState::Gallery) => {
+                let Some(gallery) = &mut self.gallery else {
+                    return Ok(());
+                };
+                let changed = match action {
+                    InputAction::Next => gallery.next(),
+                    InputAction::Previous => gallery.previous(),
+                    _ => gallery.back(),
+                };
+                if changed {
+                    self.build_gallery_ui();
+                } else if action == InputAction::Back {
+                    self.state_change_sender.send(AppState::TakePicturePrompt)?;
+                }
+            },
+            (InputAction::Keep | InputAction::Retake | InputAction::Delete, AppState::Review) => {
+                // Only the first choice counts, e.g. when the photo is kept
+                // automatically while a button is touched
+                let Some(picture) = self.reviewed_picture.take() else {
+                    return Ok(());
+                };
+                if action != InputAction::Keep {
+                    let Some(file_manager) = &self.file_manager else {
+                        anyhow::bail!(BoothError::NoStorage);
+                    };
+                    file_manager.trash(&picture)?;
+                }
+                let next_state = if action == InputAction::Retake { AppState::TakingPicture } else { AppState::TakePicturePrompt };
+                self.state_change_sender.send(next_state)?;
+            },
+            (action, state) => {
+                debug!("Action {:?} is not available in state {:?}", action, state);
+            },
+        }
+        Ok(())
+    }
+
+    /// Show the next calibration target or finish the calibration
+    fn calibration_touch(&mut self, pos: (f64, f64)) -> Result<()> {
+        let Some(calibration) = &mut self.calibration else {
+            return Ok(());
+        };
+        if calibration.is_done() {
+            return Ok(());
+        }
+
+        calibration.add_touch(pos);
+        if !calibration.is_done() {
+            self.build_calibration_ui();
+            return Ok(());
+        }
+
+        match calibration.matrix() {
+            Some(matrix) => {
+                info!("Touch calibration: {:?}", matrix);
+                self.config.touch_calibration = Some(matrix);
+                self.input.set_calibration(Some(matrix));
+                _ = photobooth::config::persist_value("touchCalibration", matrix).inspect_err(|err| {
+                    error!("Couldn't save touch calibration: {:?}", err);
+                });
+                self.state_change_sender.send(AppState::TakePicturePrompt)?;
+            },
+            None => {
+                warn!("Calibration touches don't span an area");
+                self.error = Some(BoothError::CalibrationFailed);
+                self.state_change_sender.send(AppState::Error)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Apply the changed configuration file. Texts, colors, timings and
+    /// layouts are used right away, see `restart` for the other settings. A
+    /// file that can't be read is ignored
+    fn reload_config(&mut self) -> Result<()> {
+        let Some(watcher) = &mut self.config_watcher else {
+            return Ok(());
+        };
+        let (config, problems) = match watcher.reload() {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Keeping the current configuration, the changed one is invalid: {:#}", err);
+                return Ok(());
+            },
+        };
+        for problem in &problems {
+            warn!("Configuration: {}", problem);
+        }
+        info!("Configuration changed: {:#?}", config);
+
+        let storage_changed = config.storage_device != self.config.storage_device || config.storage_sub_path != self.config.storage_sub_path;
+        if storage_changed {
+            match Self::create_file_manager(&config) {
+                Ok(file_manager) => self.file_manager = file_manager,
+                Err(err) => {
+                    error!("Keeping the current configuration, the storage of the changed one can't be used: {:#}", err);
+                    return Ok(());
+                },
+            }
+        }
+
+        if config.needs_restart(&self.config) {
+            info!("Display, camera and input settings are applied when the booth is idle");
+            self.restart = true;
+            if self.previous_config.is_none() {
+                self.previous_config = Some(self.config.clone());
+            }
+        }
+        let previous = std::mem::replace(&mut self.config, config);
+        self.config_problems = problems;
+
+        if self.state != AppState::Calibration {
+            self.input.set_calibration(self.config.touch_calibration);
+        }
+        Self::apply_text_style(&mut self.ui, &self.config);
+        self.update_backgrounds(&previous);
+        let language = if self.config.languages().contains(&self.language) { self.language.clone() } else { self.config.language.clone() };
+        self.set_language(language);
+
+        // Show the changes on the screens waiting for guests, the others pick
+        // them up when they are shown next
+        if matches!(self.state, AppState::TakePicturePrompt | AppState::Attract) {
+            self.state_change_sender.send(self.state)?;
+        }
+        Ok(())
+    }
+
+    /// Color, opacity, outline and shadow of text
+    fn apply_text_style(ui: &mut UI, config: &photobooth::config::Config) {
+        ui.set_text_color(config.text_color);
+        ui.set_text_effects(TextEffects {
+            opacity: config.text_opacity,
+            outline: config.text_outline,
+            shadow: config.text_shadow,
+        });
+    }
+
+    /// Backgrounds of the screens not showing the camera preview
+    fn create_backgrounds(config: &photobooth::config::Config, screen_size: (usize, usize)) -> HashMap<AppState, Rc<RefCell<Background>>> {
+        [AppState::TakePicturePrompt, AppState::TakePicture, AppState::Review, AppState::Gallery, AppState::Admin, AppState::Calibration, AppState::ConfigReport, AppState::Error].into_iter().map(|state| {
+            let background = Background::new(state.bg_color(config), state.background_image(config), screen_size);
+            (state, Rc::new(RefCell::new(background)))
+        }).collect()
+    }
+
+    /// Create the backgrounds whose color or image changed since `previous`.
+    /// The others are kept, so their images aren't decoded again
+    fn update_backgrounds(&mut self, previous: &photobooth::config::Config) {
+        let screen_size = (self.disp.size().0 as usize, self.disp.size().1 as usize);
+        for (state, background) in self.backgrounds.iter_mut() {
+            let color = state.bg_color(&self.config);
+            let image = state.background_image(&self.config);
+            if color != state.bg_color(previous) || image != state.background_image(previous) {
+                *background = Rc::new(RefCell::new(Background::new(color, image, screen_size)));
+            }
+        }
+    }
+
+    /// Show the texts in `language`. Languages with invalid texts are skipped
+    fn set_language(&mut self, language: String) {
+        match self.config.texts(&language) {
+            Ok(texts) => {
+                info!("Language: {}", language);
+                self.texts = texts;
+                self.language = language;
+            },
+            Err(err) => error!("{:?}", err),
+        }
+    }
+
+    /// Build the screen of `state` from its layout in the configuration
+    fn build_layout(&mut self, state: AppState, variables: &LayoutVariables) -> BuiltLayout {
+        let (elements, font_size) = match state {
+            AppState::TakePicturePrompt => (&self.config.layouts.take_picture_prompt, self.config.text_size),
+            AppState::Attract => (&self.config.layouts.attract, self.config.text_size),
+            AppState::TakingPicture => (&self.config.layouts.taking_picture, self.config.countdown_text_size),
+            AppState::TakePicture => (&self.config.layouts.take_picture, self.config.text_size),
+            AppState::Review => (&self.config.layouts.review, self.config.text_size),
+            AppState::Gallery => (&self.config.layouts.gallery, self.config.text_size),
+            AppState::Error => (&self.config.layouts.error, self.config.text_size),
+            AppState::Admin | AppState::Calibration | AppState::ConfigReport => return BuiltLayout::default(),
+        };
+        // A language button can't do anything with a single language
+        let elements: Vec<LayoutElement> = elements.iter()
+            .filter(|element| element.action != Some(InputAction::NextLanguage) || self.config.languages().len() > 1)
+            .cloned()
+            .collect();
+        let sender = self.action_sender.clone();
+        self.ui.build_layout(&elements, variables, font_size, Rc::new(move |action| {
+            _ = sender.send(action);
+        }))
+    }
+
+    /// Show the current page of the gallery, or the photo being viewed
+    fn build_gallery_ui(&mut self) {
+        self.ui.clear();
+        let Some(gallery) = &mut self.gallery else {
+            return;
+        };
+        gallery.build(&mut self.ui);
+        let position = if gallery.is_empty() { self.texts.gallery_empty_text.clone() } else { gallery.position() };
+
+        self.build_layout(AppState::Gallery, &LayoutVariables::from([
+            ("position".to_string(), position),
+            ("backText".to_string(), self.texts.back_text.clone()),
+        ]));
+
+        let sender = self.action_sender.clone();
+        self.ui.add_gesture_listener(Box::new(move |event| {
+            let action = match event {
+                InputEvent::Swipe { direction: SwipeDirection::Left, .. } => InputAction::Next,
+                InputEvent::Swipe { direction: SwipeDirection::Right, .. } => InputAction::Previous,
+                _ => return,
+            };
+            _ = sender.send(action);
+        }));
+    }
+
+    fn build_calibration_ui(&mut self) {
+        self.ui.clear();
+        let Some(target) = self.calibration.as_ref().and_then(|calibration| calibration.current_target()) else {
+            return;
+        };
+
+        let textbox = self.ui.add_text_box(
+            (0., 0.),
+            (self.disp.size().0 as f32, self.disp.size().1 as f32),
+            fontdue::layout::HorizontalAlign::Center,
+            fontdue::layout::VerticalAlign::Middle
+        );
+        textbox.borrow_mut().add_text(&self.texts.calibration_text, self.config.text_size / 2.);
+        self.ui.add_target((target.0 as f32, target.1 as f32), 60.);
+    }
+
+    /// List the problems in the configuration file, until the screen is
+    /// touched
+    fn build_config_report_ui(&mut self) {
+        let mut lines = vec!["Problems in the configuration file:".to_string(), String::new()];
+        lines.extend(self.config_problems.iter().take(MAX_REPORTED_PROBLEMS).map(|problem| format!("- {}", problem)));
+        if self.config_problems.len() > MAX_REPORTED_PROBLEMS {
+            lines.push(format!("... and {} more, see the log", self.config_problems.len() - MAX_REPORTED_PROBLEMS));
+        }
+        lines.extend([String::new(), "Touch the screen to continue".to_string()]);
+
+        let margin = self.disp.size().1 as f32 * 0.04;
+        let textbox = self.ui.add_text_box(
+            (margin, margin),
+            (self.disp.size().0 as f32 - 2. * margin, self.disp.size().1 as f32 - 2. * margin),
+            fontdue::layout::HorizontalAlign::Left,
+            fontdue::layout::VerticalAlign::Top
+        );
+        textbox.borrow_mut().add_text(lines.join("\n"), self.disp.size().1 as f32 * 0.035);
+    }
+
+    /// Dim or blank the screen and stop rendering until the screen is
+    /// touched. The run loop keeps checking the configuration file and timers
+    /// in the meantime, see `wake_up`
+    fn sleep_until_touched(&mut self) {
+        info!("Booth is idle, entering power saving mode");
+        // Not being able to turn off the screen shouldn't stop the booth
+        _ = self.set_screen_idle(true).inspect_err(|err| {
+            warn!("Couldn't turn off screen: {:?}", err);
+        });
+        self.sleeping = true;
+    }
+
+    /// Turn the screen back on after `sleep_until_touched`
+    fn wake_up(&mut self) -> Result<()> {
+        // The touch that woke the screen shouldn't be handled by the UI
+        while self.input_receiver.try_recv().is_ok() {}
+        self.ui.discard_input();
+
+        info!("Waking up from power saving mode");
+        _ = self.set_screen_idle(false).inspect_err(|err| {
+            warn!("Couldn't turn on screen: {:?}", err);
+        });
+        self.sleeping = false;
+        self.last_activity = Utc::now();
+        if self.state == AppState::Attract {
+            self.state_change_sender.send(AppState::TakePicturePrompt)?;
+        }
+        Ok(())
+    }
+
+    fn set_screen_idle(&self, idle: bool) -> Result<()> {
+        let dim = self.config.idle_action == IdleAction::Dim;
+        match &self.backlight {
+            Some(backlight) if dim => if idle {
+                backlight.set_percentage(self.config.idle_brightness)
+            } else {
+                backlight.restore()
+            },
+            _ => {
+                if dim {
+                    warn!("No backlight found to dim, blanking the screen instead");
+                }
+                self.disp.set_power(!idle)
+            }
+        }
+    }
+
     // fn set_error(&mut self, message: impl std::fmt::Display) -> Result<()> {
     //     self.error_message = Some(message.to_string());
     //     self.state_change_sender.send(AppState::Error)?;
@@ -202,79 +755,140 @@ impl<'a> App<'a> {
     // }
 
     pub fn transition(&mut self, previous_state: Option<AppState>, state: AppState) -> Result<()> {
-        self.state = state;
-        self.time_sensitive_ui.clear();
+        self.last_activity = Utc::now();
         self.ui.clear();
+        if previous_state.is_some() && self.config.transition_time > 0 {
+            // The back buffer still holds one of the last frames of the
+            // previous screen
+            let from = self.disp.back_buffer_mut()?;
+            self.cross_fade.restart(from.as_ref(), Duration::from_millis(self.config.transition_time as u64));
+        } else {
+            self.cross_fade.finish();
+        }
 
         match previous_state {
             Some(AppState::TakingPicture) => {
                 self.camera.stop_stream()?;
             },
             Some(AppState::Error) => {
-                self.error_message = None;
-            }
+                self.error = None;
+            },
+            Some(AppState::Review) => {
+                // Photos that weren't reviewed are kept
+                self.reviewed_picture = None;
+            },
+            Some(AppState::Gallery) => {
+                self.gallery = None;
+            },
+            Some(AppState::Admin) => {
+                self.admin = None;
+            },
+            Some(AppState::Attract | AppState::ConfigReport) => {
+                // The touch that ended attract mode shouldn't be handled by
+                // the prompt
+                self.ui.discard_input();
+            },
+            Some(AppState::Calibration) => {
+                // Restore the calibration in case it was aborted
+                self.calibration = None;
+                self.input.set_calibration(self.config.touch_calibration);
+            },
             Some(_) | None => {},
         }
 
+        // The prompt can't be used without a touch device, explain why instead
+        let health = self.input.health();
+        let state = match health.error() {
+            Some(error) if matches!(state, AppState::TakePicturePrompt | AppState::Attract) => {
+                warn!("Input unavailable: {:?}", health);
+                self.error = Some(error);
+                AppState::Error
+            },
+            _ => state,
+        };
+        self.state = state;
+        self.ui.set_background(self.backgrounds.get(&state).cloned());
+
         match state {
             AppState::TakePicturePrompt => {
-                let textbox = self.ui.add_text_box(
-                    (0., 0.),
-                    (self.disp.size().0 as f32, self.disp.size().1 as f32),
-                    fontdue::layout::HorizontalAlign::Center,
-                    fontdue::layout::VerticalAlign::Middle
-                );
-                let mut textbox = textbox.borrow_mut();
-                textbox.add_text(&self.config.take_picture_text, self.config.text_size);
-                let sender = self.state_change_sender.clone();
-                textbox.add_touch_listener(Box::new(move || {
-                    sender.send(AppState::TakingPicture).unwrap();
+                let variables = LayoutVariables::from([
+                    ("takePictureText".to_string(), self.texts.take_picture_text.clone()),
+                    ("galleryText".to_string(), self.texts.gallery_text.clone()),
+                    ("languageName".to_string(), self.texts.language_name.clone()),
+                ]);
+                self.build_layout(state, &variables);
+
+                let size = (self.disp.size().0 as f64, self.disp.size().1 as f64);
+                let sender = self.action_sender.clone();
+                self.ui.add_gesture_listener(Box::new(move |event| {
+                    if let InputEvent::LongPress { x, y } = *event {
+                        if x < size.0 * ADMIN_CORNER_SIZE && y < size.1 * ADMIN_CORNER_SIZE {
+                            _ = sender.send(InputAction::OpenAdminMenu);
+                        }
+                    }
                 }));
             },
+            AppState::Attract => {
+                // The next guest starts in the default language
+                if self.language != self.config.language {
+                    self.set_language(self.config.language.clone());
+                }
+                let photos = match &self.file_manager {
+                    Some(file_manager) => file_manager.recent_images(self.config.attract_photo_count).unwrap_or_else(|err| {
+                        warn!("Couldn't list recent photos: {:?}", err);
+                        Vec::new()
+                    }),
+                    None => Vec::new(),
+                };
+                let screen_size = (self.disp.size().0 as usize, self.disp.size().1 as usize);
+                let background = Background::slideshow(self.config.bg_color, photos, ImageFit::Contain, self.config.attract_slide_time, screen_size);
+                self.ui.set_background(Some(Rc::new(RefCell::new(background))));
+                self.build_layout(state, &LayoutVariables::from([
+                    ("attractText".to_string(), self.texts.attract_text.clone()),
+                ]));
+            },
             AppState::TakingPicture => {
                 if self.file_manager.is_none() || !self.file_manager.as_ref().unwrap().write_loc_exists() {
                     // Try to connect to a USB storage device first
                     self.file_manager = Self::create_file_manager(&self.config)?;
                     if self.file_manager.is_none() {
-                        anyhow::bail!(self.config.error_no_usb_device.clone());
+                        anyhow::bail!(BoothError::NoStorage);
                     }
                 }
 
                 self.camera.start_stream()?;
-                let textbox = self.ui.add_text_box(
-                    (0., 0.),
-                    (self.disp.size().0 as f32, self.disp.size().1 as f32),
-                    fontdue::layout::HorizontalAlign::Center,
-                    fontdue::layout::VerticalAlign::Middle
-                );
-                {
-                    let mut tb = textbox.borrow_mut();
-                    tb.add_text(format!("{}", self.config.countdown), self.config.countdown_text_size);
-                }
+                let countdown_variables = |countdown: u32| LayoutVariables::from([
+                    ("countdown".to_string(), countdown.to_string()),
+                ]);
+                let layout = self.build_layout(state, &countdown_variables(self.config.countdown));
 
+                // The picture is taken when the countdown shows 1
                 let countdown = self.config.countdown;
-                let countdown_text_size = self.config.countdown_text_size;
+                let end = Duration::from_secs(countdown.saturating_sub(1) as u64);
+                let mut timeline = Timeline::new();
+                for second in 1..countdown {
+                    let layout = layout.clone();
+                    timeline = timeline.at(Duration::from_secs(second as u64), move || {
+                        layout.set_variables(&countdown_variables(countdown - second));
+                    });
+                }
                 let sender = self.state_change_sender.clone();
-                self.time_sensitive_ui.push((textbox, TimeDelta::zero(), Box::new(move |prev_delta, delta, textbox| {
-                    if prev_delta.num_seconds() != delta.num_seconds() {
-                        let mut textbox = textbox.borrow_mut();
-                        textbox.clear();
-                        textbox.add_text(format!("{}", countdown - delta.num_seconds() as u32), countdown_text_size);
-
-                        if countdown - delta.num_seconds() as u32 == 1 {
-                            sender.send(AppState::TakePicture).unwrap();
-                        }
-                    }
-                })))
+                timeline = timeline.at(end, move || {
+                    _ = sender.send(AppState::TakePicture);
+                });
+                for ring in layout.countdown_rings() {
+                    timeline = timeline.animate(ring.clone(), Property::Progress(Tween::new(1., 0., end).easing(Easing::Linear)));
+                }
+                self.ui.add_timeline(timeline);
             },
             AppState::TakePicture => {
                 let Some(file_manager) = &mut self.file_manager else {
-                    anyhow::bail!(self.config.error_no_usb_device.clone());
+                    anyhow::bail!(BoothError::NoStorage);
                 };
-                let image_format = ImageFormat::Jpeg;
+                let image_format = self.config.image_format.image_format();
                 let file_name = file_manager.next_image_location(image_format.extensions_str().first().unwrap());
                 if file_name.exists() { anyhow::bail!("File {:?} already exists", file_name) }
-                let file = File::create(&file_name)?;
+                let file = File::create(&file_name).map_err(BoothError::writing)?;
                 let mut writer = BufWriter::new(file);
 
                 let (capture_sender, capture_waiter) = std::sync::mpsc::channel();
@@ -307,7 +921,7 @@ impl<'a> App<'a> {
                         _ = signal_continue.send(()); // signal continue in case camera thread is still running
                         let res = camera_thread_handle.join().map_err(|err| anyhow!(format!("{:?}", err))).and_then(|res| res.map_err(|err| anyhow!(err)));
                         let err = match res {
-                            Ok(_) => anyhow!(err),
+                            Ok(_) => anyhow!(err).context(BoothError::CaptureFailed),
                             Err(err) => err,
                         };
                         return Err(err);
@@ -329,18 +943,13 @@ impl<'a> App<'a> {
 
                 // Display done message
                 {
-                    self.disp.clear(self.config.bg_color)?;
+                    let done_sentence = self.texts.done_sentence();
+                    self.build_layout(state, &LayoutVariables::from([
+                        ("doneSentence".to_string(), done_sentence),
+                    ]));
 
                     let mut back_buffer = self.disp.back_buffer_mut()?;
                     let buffer = back_buffer.as_mut();
-
-                    let textbox = self.ui.add_text_box(
-                        (0., 0.),
-                        (size.0 as f32, size.1 as f32),
-                        fontdue::layout::HorizontalAlign::Center,
-                        fontdue::layout::VerticalAlign::Middle
-                    );
-                    textbox.borrow_mut().add_text(&self.config.done_sentences[rand::random_range(0..self.config.done_sentences.len())], self.config.text_size);
                     self.ui.render(buffer);
                 }
 
@@ -373,11 +982,18 @@ impl<'a> App<'a> {
 
                 let file = File::open(&file_name)?;
                 if file.metadata()?.len() == 0 {
-                    anyhow::bail!(self.config.error_empty_file_witten.clone());
+                    anyhow::bail!(BoothError::EmptyFile);
                 }
 
                 info!("Picture written to {:?}", file_name);
 
+                if self.config.review {
+                    self.reviewed_picture = Some(file_name);
+                    self.review_image = Some(resized_image);
+                    self.state_change_sender.send(AppState::Review)?;
+                    return Ok(());
+                }
+
                 let dt = Utc::now() - t;
                 let sleep_time = std::time::Duration::from_secs(self.config.show_image_time as u64);
                 let sleep_time = (sleep_time.as_millis() as u64).saturating_sub(dt.num_milliseconds() as u64);
@@ -386,14 +1002,65 @@ impl<'a> App<'a> {
 
                 self.state_change_sender.send(AppState::TakePicturePrompt)?;
             },
+            AppState::Gallery => {
+                let Some(file_manager) = &self.file_manager else {
+                    anyhow::bail!(BoothError::NoStorage);
+                };
+                let screen_size = (self.disp.size().0 as usize, self.disp.size().1 as usize);
+                self.gallery = Some(Gallery::new(
+                    file_manager.recent_images(usize::MAX)?,
+                    file_manager.thumbnails(),
+                    self.config.gallery_columns,
+                    self.config.gallery_rows,
+                    screen_size,
+                ));
+                self.build_gallery_ui();
+            },
+            AppState::Review => {
+                if let Some(image) = self.review_image.take() {
+                    let size = (self.disp.size().0 as f32, self.disp.size().1 as f32);
+                    self.ui.add_image((0., 0.), size, &image, ImageFit::Contain);
+                }
+                let layout = self.build_layout(state, &LayoutVariables::from([
+                    ("keepText".to_string(), self.texts.keep_text.clone()),
+                    ("retakeText".to_string(), self.texts.retake_text.clone()),
+                    ("deleteText".to_string(), self.texts.delete_text.clone()),
+                ]));
+
+                let timeout = Duration::from_secs(self.config.review_timeout as u64);
+                let sender = self.action_sender.clone();
+                let mut timeline = Timeline::new().at(timeout, move || {
+                    _ = sender.send(InputAction::Keep);
+                });
+                for ring in layout.countdown_rings() {
+                    timeline = timeline.animate(ring.clone(), Property::Progress(Tween::new(1., 0., timeout)));
+                }
+                self.ui.add_timeline(timeline);
+            },
+            AppState::Admin => {
+                let Some(pin) = self.config.admin_pin.as_ref().map(|pin| pin.0.clone()) else {
+                    anyhow::bail!("The admin menu is disabled");
+                };
+                let screen_size = (self.disp.size().0 as usize, self.disp.size().1 as usize);
+                let admin = AdminMenu::new(pin, self.admin_settings(), self.admin_status(), screen_size);
+                admin.build(&mut self.ui);
+                self.admin = Some(admin);
+            },
+            AppState::Calibration => {
+                // Touches are measured without the current correction
+                self.input.set_calibration(None);
+                self.calibration = Some(CalibrationRoutine::new((self.disp.size().0 as f64, self.disp.size().1 as f64)));
+                self.build_calibration_ui();
+            },
+            AppState::ConfigReport => {
+                self.build_config_report_ui();
+            },
             AppState::Error => {
-                let textbox = self.ui.add_text_box(
-                    (0., 0.),
-                    (self.disp.size().0 as f32, self.disp.size().1 as f32),
-                    fontdue::layout::HorizontalAlign::Center,
-                    fontdue::layout::VerticalAlign::Middle
-                );
-                textbox.borrow_mut().add_text(self.error_message.as_ref().unwrap_or(&self.config.unknown_error_message), self.config.text_size);
+                let error = self.error.unwrap_or(BoothError::Unknown);
+                self.build_layout(state, &LayoutVariables::from([
+                    ("errorMessage".to_string(), self.texts.error_message(error).to_string()),
+                    ("errorCode".to_string(), error.code().to_string()),
+                ]));
                 let sleep_time = self.config.error_message_time;
                 let sender = self.state_change_sender.clone();
                 std::thread::spawn(move || {
@@ -408,9 +1075,158 @@ impl<'a> App<'a> {
         Ok(())
     }
 
+    /// Apply and save the changes made in the admin menu
+    fn handle_admin_requests(&mut self, requests: Vec<AdminRequest>) -> Result<()> {
+        let mut message = None;
+        for request in requests {
+            info!("Admin menu: {:?}", request);
+            let saved = match request {
+                AdminRequest::SetCountdown(countdown) => {
+                    self.config.countdown = countdown;
+                    photobooth::config::persist_value("countdown", countdown)
+                },
+                AdminRequest::SetPhotoFormat(format) => {
+                    self.config.image_format = format;
+                    photobooth::config::persist_value("imageFormat", format)
+                },
+                AdminRequest::SetText { key, text } => {
+                    if let Some(value) = self.texts.text_mut(key) {
+                        *value = text.clone();
+                    }
+                    // Keep the change when switching languages
+                    self.config.texts.entry(self.language.clone()).or_default().insert(key.into(), text.clone().into());
+                    photobooth::config::persist_nested_value(&["texts", &self.language, key], text)
+                },
+                AdminRequest::SelectStorage(name) => {
+                    // Only switch, and save the choice, once the device works
+                    let previous = self.config.storage_device.replace(name.clone());
+                    match Self::create_file_manager(&self.config) {
+                        Ok(file_manager) => {
+                            self.file_manager = file_manager;
+                            photobooth::config::persist_value("storageDevice", name)
+                        },
+                        Err(err) => {
+                            error!("Couldn't use storage device {}: {:?}", name, err);
+                            self.config.storage_device = previous;
+                            message = Some(format!("Couldn't use {}: {}", name, err));
+                            Ok(())
+                        },
+                    }
+                },
+                AdminRequest::Eject => {
+                    let usb_devices = files::usb::StorageDevices::collect();
+                    let drive = self.file_manager.as_ref().and_then(|file_manager| usb_devices.drive_containing(file_manager.location()));
+                    message = Some(match drive {
+                        Some(drive) => match files::usb::eject(drive.mount_point()) {
+                            Ok(()) => {
+                                self.file_manager = None;
+                                format!("{} can be removed", drive.name().to_string_lossy())
+                            },
+                            Err(err) => {
+                                error!("Couldn't eject {:?}: {:?}", drive.mount_point(), err);
+                                format!("Couldn't eject: {}", err)
+                            },
+                        },
+                        None => "No storage device to eject".to_string(),
+                    });
+                    Ok(())
+                },
+                AdminRequest::Shutdown => {
+                    info!("Shutting down");
+                    if let Err(err) = Command::new("systemctl").arg("poweroff").spawn() {
+                        error!("Couldn't shut down: {:?}", err);
+                        message = Some(format!("Couldn't shut down: {}", err));
+                    }
+                    Ok(())
+                },
+                AdminRequest::Calibrate => {
+                    self.state_change_sender.send(AppState::Calibration)?;
+                    Ok(())
+                },
+                AdminRequest::Close => {
+                    self.state_change_sender.send(AppState::TakePicturePrompt)?;
+                    Ok(())
+                },
+                AdminRequest::WrongPin => {
+                    if self.pin_attempts.failed(Instant::now()) {
+                        warn!("Too many wrong PINs, locking the admin menu");
+                        self.state_change_sender.send(AppState::TakePicturePrompt)?;
+                    }
+                    Ok(())
+                },
+                AdminRequest::PinAccepted => {
+                    self.pin_attempts.succeeded();
+                    Ok(())
+                },
+            };
+            if let Err(err) = saved {
+                error!("Couldn't save setting: {:?}", err);
+                message = Some(format!("Couldn't save setting: {}", err));
+            }
+        }
+
+        let (settings, status) = (self.admin_settings(), self.admin_status());
+        if let Some(admin) = &mut self.admin {
+            admin.refresh(settings, status);
+            if let Some(message) = message {
+                admin.set_message(message);
+            }
+            admin.build(&mut self.ui);
+        }
+        Ok(())
+    }
+
+    fn admin_settings(&mut self) -> AdminSettings {
+        let usb_devices = files::usb::StorageDevices::collect();
+        let storage_device = self.file_manager.as_ref()
+            .and_then(|file_manager| usb_devices.drive_containing(file_manager.location()))
+            .map(|drive| drive.name().to_string_lossy().to_string());
+        AdminSettings {
+            countdown: self.config.countdown,
+            photo_format: self.config.image_format,
+            texts: Texts::EDITABLE.into_iter()
+                .map(|key| (key, self.texts.text_mut(key).cloned().unwrap_or_default()))
+                .collect(),
+            storage_devices: usb_devices.drives().iter().map(|drive| drive.name().to_string_lossy().to_string()).collect(),
+            storage_device,
+        }
+    }
+
+    fn admin_status(&self) -> AdminStatus {
+        let usb_devices = files::usb::StorageDevices::collect();
+        let storage = match &self.file_manager {
+            Some(file_manager) => match usb_devices.drive_containing(file_manager.location()) {
+                Some(drive) => format!(
+                    "{} ({:.1} of {:.1} GB free)",
+                    file_manager.location().display(),
+                    drive.available_space() as f64 / 1e9,
+                    drive.total_space() as f64 / 1e9,
+                ),
+                None => file_manager.location().display().to_string(),
+            },
+            None => "not connected".to_string(),
+        };
+        let photo_count = self.file_manager.as_ref()
+            .and_then(|file_manager| file_manager.recent_images(usize::MAX).ok())
+            .map_or(0, |images| images.len());
+        AdminStatus {
+            storage,
+            photo_count,
+            camera: self.camera.id(),
+            log: read_log_tail(ADMIN_LOG_LINES).unwrap_or_else(|err| vec![format!("Couldn't read log: {}", err)]),
+        }
+    }
+
     fn create_file_manager(config: &photobooth::config::Config) -> Result<Option<FileManager>> {
         let usb_devices = files::usb::StorageDevices::collect();
-        usb_devices.drives().first().map(|drive| {
+        let selected = config.storage_device.as_ref().and_then(|name| {
+            let drive = usb_devices.drive(name);
+            if drive.is_none() {
+                warn!("Storage device {:?} not found, using the first one", name);
+            }
+            drive
+        });
+        selected.or(usb_devices.drives().first().copied()).map(|drive| {
             info!("Storage device: {:?}", drive.name());
             info!("Storage available: {}", drive.available_space());
             config.storage_sub_path.clone().map(|subpath| drive.mount_point().join(subpath))
@@ -422,6 +1238,19 @@ impl<'a> App<'a> {
     }
 }
 
+/// The last `count` lines of the log file
+fn read_log_tail(count: usize) -> io::Result<Vec<String>> {
+    let mut file = File::open(LOG_FILE)?;
+    // Lines are short, the end of the file is enough
+    let length = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(length.saturating_sub(8 * 1024)))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes);
+    let lines: Vec<String> = text.lines().map(str::to_string).collect();
+    Ok(lines[lines.len().saturating_sub(count)..].to_vec())
+}
+
 fn configure_logging() -> Result<()> {
     let stdout_log = log4rs::append::console::ConsoleAppender::builder()
         .encoder(Box::new(log4rs::encode::pattern::PatternEncoder::new("{d(%H:%M:%S)} {h({l})}: {m}\n")))
@@ -430,7 +1259,7 @@ fn configure_logging() -> Result<()> {
     let logfile = match log4rs::append::rolling_file::RollingFileAppender::builder()
         .encoder(Box::new(log4rs::encode::pattern::PatternEncoder::new("{d} {M}::{f}:{L} {l}: {m}\n")))
         .build(
-            "/var/log/photobooth.log",
+            LOG_FILE,
             Box::new(log4rs::append::rolling_file::policy::compound::CompoundPolicy::new(
                 Box::new(log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger::new(10 * 1024 * 1024)), // 10 MB
                 Box::new(log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller::builder().build("/var/log/photobooth.{}.log", 5).unwrap()) // keep 5 old files
@@ -466,32 +1295,53 @@ fn configure_logging() -> Result<()> {
     Ok(())
 }
 
-fn get_config() -> Result<photobooth::config::Config> {
-    let configuration_path = std::env::var_os("PH_CONFIG").unwrap_or("config.yaml".into());
+fn get_config() -> (photobooth::config::Config, Vec<ConfigProblem>) {
+    let configuration_path = photobooth::config::config_path();
     info!("Reading configuration from {}", configuration_path.to_string_lossy());
-    let config: photobooth::config::Config = if std::fs::exists(&configuration_path)? {
-        let config_file = File::open(configuration_path)?;
-        let config_reader = BufReader::new(config_file);
-        serde_yaml::from_reader(config_reader)?
-    } else {
-        info!("Configuration file not found, using default");
-        Default::default()
-    };
+    let (config, problems) = photobooth::config::load(&configuration_path);
+    for problem in &problems {
+        warn!("Configuration: {}", problem);
+    }
 
     info!("Config: {:#?}", config);
 
-    return Ok(config);
+    return (config, problems);
 }
 
 fn main() -> Result<()> {
     configure_logging()?;
 
-    let config = get_config()?;
+    let (config, config_problems) = get_config();
 
     let camera_manager = CameraManager::acquire()?;
 
-    let mut app = App::new(config, &camera_manager)?;
-    app.run_with_error();
+    let mut app = App::new(config, config_problems, &camera_manager)?;
+    if std::env::args().any(|arg| arg == "--calibrate") {
+        app.state = AppState::Calibration;
+    }
+    loop {
+        app.run_with_error();
+        if !app.restart {
+            break;
+        }
+        // The display and camera can only be opened again once released
+        let config = std::mem::take(&mut app.config);
+        let config_problems = std::mem::take(&mut app.config_problems);
+        let previous_config = app.previous_config.take();
+        drop(app);
+        app = match (App::new(config, config_problems, &camera_manager), previous_config) {
+            (Ok(app), _) => app,
+            (Err(err), Some(previous_config)) => {
+                let error = BoothError::of(&err);
+                error!("{}: Couldn't apply the changed configuration, going back to the previous one: {:#}", error.code(), err);
+                let mut app = App::new(previous_config, Vec::new(), &camera_manager)?;
+                app.error = Some(error);
+                app.state = AppState::Error;
+                app
+            },
+            (Err(err), None) => return Err(err),
+        };
+    }
 
     Ok(())
 }
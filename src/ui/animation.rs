@@ -0,0 +1,274 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::color::Color;
+use super::UIElement;
+
+/// How an animation progresses over its duration
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    EaseInCubic,
+    EaseOutCubic,
+    EaseInOutCubic,
+    /// Overshoots the end a little before settling
+    EaseOutBack,
+}
+
+impl Easing {
+    /// Map linear progress `t` (0 - 1) to eased progress
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1. - (1. - t) * (1. - t),
+            Easing::EaseInOut => if t < 0.5 { 2. * t * t } else { 1. - (-2. * t + 2.).powi(2) / 2. },
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => 1. - (1. - t).powi(3),
+            Easing::EaseInOutCubic => if t < 0.5 { 4. * t * t * t } else { 1. - (-2. * t + 2.).powi(3) / 2. },
+            Easing::EaseOutBack => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.;
+                1. + C3 * (t - 1.).powi(3) + C1 * (t - 1.).powi(2)
+            },
+        }
+    }
+}
+
+/// Values that can be interpolated
+pub trait Lerp: Copy {
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for (f32, f32) {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        (self.0.lerp(to.0, t), self.1.lerp(to.1, t))
+    }
+}
+
+/// Interpolated per channel
+impl Lerp for Color {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self.mix(to, t)
+    }
+}
+
+/// A value going from `from` to `to` in `duration`, after `delay`
+#[derive(Debug, Clone, Copy)]
+pub struct Tween<T: Lerp> {
+    pub from: T,
+    pub to: T,
+    pub delay: Duration,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: Duration) -> Self {
+        Tween {
+            from,
+            to,
+            delay: Duration::ZERO,
+            duration,
+            easing: Easing::default(),
+        }
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// The time at which the tween reaches `to`
+    pub fn end(&self) -> Duration {
+        self.delay + self.duration
+    }
+
+    pub fn value_at(&self, elapsed: Duration) -> T {
+        let t = if elapsed <= self.delay {
+            0.
+        } else if self.duration.is_zero() || elapsed >= self.end() {
+            1.
+        } else {
+            (elapsed - self.delay).as_secs_f32() / self.duration.as_secs_f32()
+        };
+        self.from.lerp(self.to, self.easing.apply(t))
+    }
+}
+
+/// A property of an element and how it changes
+#[derive(Debug, Clone, Copy)]
+pub enum Property {
+    Position(Tween<(f32, f32)>),
+    Scale(Tween<f32>),
+    Opacity(Tween<f32>),
+    Color(Tween<Color>),
+    Progress(Tween<f32>),
+}
+
+impl Property {
+    fn end(&self) -> Duration {
+        match self {
+            Property::Position(tween) => tween.end(),
+            Property::Scale(tween) | Property::Opacity(tween) | Property::Progress(tween) => tween.end(),
+            Property::Color(tween) => tween.end(),
+        }
+    }
+
+    fn apply(&self, element: &mut dyn UIElement, elapsed: Duration) {
+        match self {
+            Property::Position(tween) => element.set_position(tween.value_at(elapsed)),
+            Property::Scale(tween) => element.set_scale(tween.value_at(elapsed)),
+            Property::Opacity(tween) => element.set_opacity(tween.value_at(elapsed)),
+            Property::Color(tween) => element.set_color(tween.value_at(elapsed)),
+            Property::Progress(tween) => element.set_progress(tween.value_at(elapsed)),
+        }
+    }
+}
+
+struct Track {
+    element: Rc<RefCell<dyn UIElement>>,
+    property: Property,
+}
+
+struct Cue {
+    time: Duration,
+    callback: Box<dyn FnMut()>,
+    fired: bool,
+}
+
+/// Animates properties of elements and calls functions at given times.
+/// Timelines are added to a `UI`, which advances them every frame
+#[derive(Default)]
+pub struct Timeline {
+    tracks: Vec<Track>,
+    cues: Vec<Cue>,
+    elapsed: Duration,
+    /// Start over when the end is reached
+    looping: bool,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn animate(mut self, element: Rc<RefCell<dyn UIElement>>, property: Property) -> Self {
+        self.tracks.push(Track { element, property });
+        self
+    }
+
+    /// Call `callback` once when `time` is reached
+    pub fn at(mut self, time: Duration, callback: impl FnMut() + 'static) -> Self {
+        self.cues.push(Cue {
+            time,
+            callback: Box::new(callback),
+            fired: false,
+        });
+        self
+    }
+
+    /// The time at which all tweens and cues are done
+    pub fn duration(&self) -> Duration {
+        let tracks = self.tracks.iter().map(|track| track.property.end());
+        let cues = self.cues.iter().map(|cue| cue.time);
+        tracks.chain(cues).max().unwrap_or_default()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.elapsed >= self.duration()
+    }
+
+    /// Move the timeline forward by `delta`, updating the animated elements
+    /// and calling the cues that were passed
+    pub fn advance(&mut self, delta: Duration) {
+        self.elapsed += delta;
+        let duration = self.duration();
+        if self.looping && !duration.is_zero() && self.elapsed >= duration {
+            self.elapsed = Duration::from_secs_f64(self.elapsed.as_secs_f64() % duration.as_secs_f64());
+            self.cues.iter_mut().for_each(|cue| cue.fired = false);
+        }
+
+        for track in &self.tracks {
+            track.property.apply(&mut *track.element.borrow_mut(), self.elapsed);
+        }
+        for cue in self.cues.iter_mut().filter(|cue| !cue.fired && cue.time <= self.elapsed) {
+            cue.fired = true;
+            (cue.callback)();
+        }
+    }
+}
+
+/// Fades from a snapshot of the previous screen to the current one
+pub struct CrossFade {
+    from: Vec<u8>,
+    tween: Tween<f32>,
+    elapsed: Duration,
+}
+
+impl CrossFade {
+    /// `from` is the last frame of the previous screen, in the format of the
+    /// buffers the UI is rendered to
+    pub fn new(from: Vec<u8>, duration: Duration) -> Self {
+        CrossFade {
+            from,
+            tween: Tween::new(1., 0., duration).easing(Easing::EaseInOut),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Fade from another snapshot, reusing the memory of the previous one
+    pub fn restart(&mut self, from: &[u8], duration: Duration) {
+        self.from.clear();
+        self.from.extend_from_slice(from);
+        self.tween = Tween::new(1., 0., duration).easing(Easing::EaseInOut);
+        self.elapsed = Duration::ZERO;
+    }
+
+    /// Skip to the end of the fade
+    pub fn finish(&mut self) {
+        self.elapsed = self.tween.end();
+    }
+
+    pub fn advance(&mut self, delta: Duration) {
+        self.elapsed += delta;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.tween.end()
+    }
+
+    /// Blend the previous screen over the rendered frame in `buffer`
+    pub fn render(&self, buffer: &mut [u8]) {
+        let opacity = (self.tween.value_at(self.elapsed) * 256.).round() as u32;
+        if opacity == 0 || self.from.len() != buffer.len() {
+            return;
+        }
+        for (dst, src) in buffer.iter_mut().zip(self.from.iter()) {
+            *dst = ((*src as u32 * opacity + *dst as u32 * (256 - opacity)) >> 8) as u8;
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+
+use log::*;
+
+use crate::color::Color;
+use super::{CrossFade, ImageFit};
+use super::draw::blend_rgba_image;
+use super::image::fit_image;
+
+/// Time it takes for one slide of a slideshow to fade into the next
+const SLIDE_FADE_TIME: Duration = Duration::from_secs(1);
+
+/// An image, or a folder of images shown as a slideshow, behind a screen
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundImage {
+    /// A PNG or JPEG file, or a folder of them
+    pub path: PathBuf,
+    /// `contain` leaves the background color around the image, `cover`
+    /// fills the screen
+    #[serde(default = "default_fit")]
+    pub fit: ImageFit,
+    /// Seconds each image of a slideshow is shown
+    #[serde(default = "default_slide_time")]
+    pub slide_time: f32,
+}
+
+fn default_fit() -> ImageFit {
+    ImageFit::Cover
+}
+
+impl BackgroundImage {
+    pub const DEFAULT_SLIDE_TIME: f32 = 8.;
+}
+
+fn default_slide_time() -> f32 {
+    BackgroundImage::DEFAULT_SLIDE_TIME
+}
+
+/// What is drawn behind the elements of a screen: a color, an image or a
+/// looping slideshow. Images are rendered into screen sized frames once, so
+/// drawing the background is a copy
+pub struct Background {
+    /// Shown around and through the image
+    color: Color,
+    size: (usize, usize),
+    fit: ImageFit,
+    /// The current image on top of `color`, in the format of the display
+    frame: Option<Vec<u8>>,
+    /// Images of the slideshow, empty when showing a single image
+    slides: Vec<PathBuf>,
+    next_slide: usize,
+    slide_time: Duration,
+    elapsed: Duration,
+    /// The next slide, rendered on another thread so decoding large photos
+    /// doesn't stall the screen
+    loading: Option<Receiver<Option<Vec<u8>>>>,
+    fade: Option<CrossFade>,
+}
+
+impl Background {
+    /// A background filled with `color`, with `image` on top of
+    /// it when given. Images that can't be loaded are skipped
+    pub fn new(color: Color, image: Option<&BackgroundImage>, size: (usize, usize)) -> Self {
+        let Some(image) = image else {
+            return Self::slideshow(color, Vec::new(), ImageFit::default(), 0., size);
+        };
+
+        let slides = if image.path.is_dir() {
+            let slides = list_images(&image.path);
+            if slides.is_empty() {
+                warn!("No images found in background folder {:?}", image.path);
+            }
+            slides
+        } else {
+            vec![image.path.clone()]
+        };
+        Self::slideshow(color, slides, image.fit, image.slide_time, size)
+    }
+
+    /// A background cycling through `slides`, each shown for `slide_time`
+    /// seconds
+    pub fn slideshow(color: Color, slides: Vec<PathBuf>, fit: ImageFit, slide_time: f32, size: (usize, usize)) -> Self {
+        let mut background = Background {
+            color,
+            size,
+            fit,
+            frame: None,
+            slides,
+            next_slide: 0,
+            slide_time: Duration::from_secs_f32(slide_time.max(0.)),
+            elapsed: Duration::ZERO,
+            loading: None,
+            fade: None,
+        };
+
+        // Show the first image right away, the others are loaded while the
+        // slideshow is running
+        while background.frame.is_none() && background.next_slide < background.slides.len() {
+            background.frame = render_frame(&background.slides[background.next_slide], color, size, fit);
+            background.next_slide += 1;
+        }
+        if background.slides.len() < 2 {
+            background.slides.clear();
+        }
+        background.next_slide %= background.slides.len().max(1);
+
+        background
+    }
+
+    /// Move the slideshow forward by `delta`
+    pub fn advance(&mut self, delta: Duration) {
+        self.elapsed += delta;
+        if let Some(fade) = &mut self.fade {
+            fade.advance(delta);
+            if fade.is_finished() {
+                self.fade = None;
+            }
+        }
+        if self.slides.is_empty() {
+            return;
+        }
+
+        let receiver = self.loading.get_or_insert_with(|| {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let (path, color, size, fit) = (self.slides[self.next_slide].clone(), self.color, self.size, self.fit);
+            std::thread::spawn(move || {
+                _ = sender.send(render_frame(&path, color, size, fit));
+            });
+            receiver
+        });
+        if self.elapsed < self.slide_time {
+            return;
+        }
+
+        match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return,
+            Ok(Some(frame)) => {
+                if let Some(previous) = self.frame.replace(frame) {
+                    self.fade = Some(CrossFade::new(previous, SLIDE_FADE_TIME));
+                }
+            },
+            // Already logged, skip the slide
+            Ok(None) | Err(TryRecvError::Disconnected) => {},
+        }
+        self.loading = None;
+        self.elapsed = Duration::ZERO;
+        self.next_slide = (self.next_slide + 1) % self.slides.len();
+    }
+
+    pub fn render(&self, buffer: &mut [u8]) {
+        match &self.frame {
+            Some(frame) => {
+                let len = frame.len().min(buffer.len());
+                buffer[..len].copy_from_slice(&frame[..len]);
+            },
+            None => fill(buffer, self.color),
+        }
+        if let Some(fade) = &self.fade {
+            fade.render(buffer);
+        }
+    }
+}
+
+/// The PNG and JPEG files in `folder`, sorted by name
+fn list_images(folder: &Path) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!("Couldn't read background folder {:?}: {}", folder, err);
+            return Vec::new();
+        },
+    };
+    let mut images: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+            ["png", "jpg", "jpeg"].contains(&extension.to_ascii_lowercase().as_str())
+        })
+        .collect();
+    images.sort();
+    images
+}
+
+/// Draw the image at `path` over `color` in a screen sized buffer
+fn render_frame(path: &Path, color: Color, size: (usize, usize), fit: ImageFit) -> Option<Vec<u8>> {
+    let image = match ::image::open(path) {
+        Ok(image) => image.to_rgba8(),
+        Err(err) => {
+            warn!("Couldn't load background {:?}: {}", path, err);
+            return None;
+        },
+    };
+
+    let mut frame = vec![0; size.0 * size.1 * 4];
+    fill(&mut frame, color);
+    let (scaled, offset) = fit_image(&image, (size.0 as f32, size.1 as f32), fit);
+    blend_rgba_image(&mut frame, size, &scaled, offset, 1.);
+    Some(frame)
+}
+
+/// Fill an XRGB8888 buffer with `color`
+fn fill(buffer: &mut [u8], color: Color) {
+    for pixel in buffer.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&color.to_xrgb8888());
+    }
+}
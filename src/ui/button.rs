@@ -0,0 +1,113 @@
+use fontdue::layout::{HorizontalAlign, VerticalAlign};
+
+use crate::color::Color;
+use super::{Fonts, TextBox, TextEffects, TouchEventListener, UIElement};
+use super::draw::fill_rounded_rect;
+use super::glyph_cache::SharedGlyphCache;
+
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ButtonStyle {
+    pub background: Color,
+    /// Background while the button is being touched
+    pub pressed_background: Color,
+    pub border_color: Color,
+    /// Border width in pixels, 0 for no border
+    pub border_width: f32,
+    pub radius: f32,
+}
+
+impl Default for ButtonStyle {
+    fn default() -> Self {
+        ButtonStyle {
+            background: Color::from_argb(0x40000000),
+            pressed_background: Color::from_argb(0x80000000),
+            border_color: Color::WHITE,
+            border_width: 3.,
+            radius: 20.,
+        }
+    }
+}
+
+pub struct Button {
+    pos: (f32, f32),
+    size: (f32, f32),
+    style: ButtonStyle,
+    label: TextBox,
+    pressed: bool,
+    scale: f32,
+    opacity: f32,
+    touch_listeners: Vec<TouchEventListener>,
+}
+
+impl Button {
+    pub(super) fn new(fonts: Fonts, glyph_cache: SharedGlyphCache, pos: (f32, f32), size: (f32, f32), style: ButtonStyle, text_color: Color, text_effects: TextEffects) -> Button {
+        Button {
+            pos,
+            size,
+            style,
+            label: TextBox::new(fonts, glyph_cache, pos, size, HorizontalAlign::Center, VerticalAlign::Middle, text_color, text_effects),
+            pressed: false,
+            scale: 1.,
+            opacity: 1.,
+            touch_listeners: Vec::new(),
+        }
+    }
+
+    pub fn set_label(&mut self, text: impl AsRef<str>, font_size: f32) {
+        self.label.clear();
+        self.label.add_text(text, font_size);
+    }
+
+    pub fn set_text_color(&mut self, color: Color) {
+        self.label.set_color(color);
+    }
+}
+
+impl UIElement for Button {
+    fn render(&self, buffer: &mut [u8], buffer_size: (usize, usize)) {
+        let background = if self.pressed { self.style.pressed_background } else { self.style.background };
+        let border = (self.style.border_width > 0.)
+            .then_some((self.style.border_width * self.scale, self.style.border_color.with_opacity(self.opacity)));
+        let size = (self.size.0 * self.scale, self.size.1 * self.scale);
+        let pos = (self.pos.0 + (self.size.0 - size.0) / 2., self.pos.1 + (self.size.1 - size.1) / 2.);
+        fill_rounded_rect(buffer, buffer_size, pos, size, self.style.radius * self.scale, background.with_opacity(self.opacity), border);
+        self.label.render(buffer, buffer_size);
+    }
+
+    fn add_touch_listener(&mut self, cb: TouchEventListener) {
+        self.touch_listeners.push(cb)
+    }
+
+    fn touch_listeners(&self) -> &Vec<TouchEventListener> {
+        &self.touch_listeners
+    }
+
+    fn is_inside(&self, x: f32, y: f32) -> bool {
+        x >= self.pos.0 && y >= self.pos.1 && x <= self.pos.0 + self.size.0 && y <= self.pos.1 + self.size.1
+    }
+
+    fn set_pressed(&mut self, pressed: bool) {
+        self.pressed = pressed;
+    }
+
+    fn set_position(&mut self, pos: (f32, f32)) {
+        self.pos = pos;
+        self.label.set_position(pos);
+    }
+
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.);
+        self.label.set_scale(scale);
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+        self.label.set_opacity(opacity);
+    }
+
+    /// The background color
+    fn set_color(&mut self, color: Color) {
+        self.style.background = color;
+    }
+}
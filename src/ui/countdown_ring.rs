@@ -0,0 +1,111 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::color::Color;
+use super::{TouchEventListener, UIElement};
+use super::draw::blend_pixel;
+
+/// A ring that empties clockwise, starting at the top, as its progress goes
+/// from 1 to 0. Drawn in the largest circle fitting its rectangle
+pub struct CountdownRing {
+    pos: (f32, f32),
+    size: (f32, f32),
+    color: Color,
+    thickness: f32,
+    /// The part of the ring that is drawn, from 0 to 1
+    progress: f32,
+    scale: f32,
+    opacity: f32,
+    touch_listeners: Vec<TouchEventListener>,
+}
+
+impl CountdownRing {
+    pub(super) fn new(pos: (f32, f32), size: (f32, f32), color: Color, thickness: f32) -> CountdownRing {
+        CountdownRing {
+            pos,
+            size,
+            color,
+            thickness,
+            progress: 1.,
+            scale: 1.,
+            opacity: 1.,
+            touch_listeners: Vec::new(),
+        }
+    }
+}
+
+impl UIElement for CountdownRing {
+    fn render(&self, buffer: &mut [u8], buffer_size: (usize, usize)) {
+        let progress = self.progress.clamp(0., 1.);
+        if progress <= 0. {
+            return;
+        }
+
+        let center = (self.pos.0 + self.size.0 / 2., self.pos.1 + self.size.1 / 2.);
+        let thickness = self.thickness * self.scale;
+        let outer = self.size.0.min(self.size.1) / 2. * self.scale;
+        let radius = outer - thickness / 2.;
+        let end_angle = progress * TAU;
+        let color = self.color.with_opacity(self.opacity);
+        let alpha = color.alpha();
+
+        let x0 = (center.0 - outer).floor().max(0.) as i32;
+        let y0 = (center.1 - outer).floor().max(0.) as i32;
+        let x1 = (center.0 + outer).ceil() as i32;
+        let y1 = (center.1 + outer).ceil() as i32;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (dx, dy) = (x as f32 + 0.5 - center.0, y as f32 + 0.5 - center.1);
+                let distance = dx.hypot(dy);
+                let radial = (thickness / 2. + 0.5 - (distance - radius).abs()).clamp(0., 1.);
+                if radial <= 0. {
+                    continue;
+                }
+
+                // Clockwise from the top, in screen coordinates
+                let angle = dx.atan2(-dy).rem_euclid(TAU);
+                let coverage = if progress >= 1. {
+                    radial
+                } else {
+                    // Anti-alias both ends of the arc by their distance in
+                    // pixels
+                    let end = (0.5 - (angle - end_angle) * distance).clamp(0., 1.);
+                    let start = if angle < PI { (angle * distance + 0.5).min(1.) } else { 1. };
+                    radial * end * start
+                };
+                blend_pixel(buffer, buffer_size, (x, y), color, alpha * coverage);
+            }
+        }
+    }
+
+    fn add_touch_listener(&mut self, cb: TouchEventListener) {
+        self.touch_listeners.push(cb)
+    }
+
+    fn touch_listeners(&self) -> &Vec<TouchEventListener> {
+        &self.touch_listeners
+    }
+
+    fn is_inside(&self, x: f32, y: f32) -> bool {
+        x >= self.pos.0 && y >= self.pos.1 && x <= self.pos.0 + self.size.0 && y <= self.pos.1 + self.size.1
+    }
+
+    fn set_position(&mut self, pos: (f32, f32)) {
+        self.pos = pos;
+    }
+
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.);
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_progress(&mut self, progress: f32) {
+        self.progress = progress;
+    }
+}
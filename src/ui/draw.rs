@@ -0,0 +1,152 @@
+//! Drawing primitives shared by the UI elements, on XRGB8888 buffers
+
+use crate::color::Color;
+
+/// Write a pixel, ignoring the alpha of `color`
+#[inline]
+pub(super) fn put_pixel(fb: &mut [u8], fb_size: (usize, usize), pos: (i32, i32), color: Color) {
+    if pos.0 < 0 || pos.1 < 0 || pos.0 as usize >= fb_size.0 || pos.1 as usize >= fb_size.1 {
+        return;
+    }
+    let index = (pos.1 as usize * fb_size.0 + pos.0 as usize) * 4;
+    fb[index..index + 4].copy_from_slice(&color.to_xrgb8888());
+}
+
+/// Blend the RGB part of `color` over the pixel at `pos` with the given
+/// opacity (0 - 1)
+#[inline]
+pub(super) fn blend_pixel(fb: &mut [u8], fb_size: (usize, usize), pos: (i32, i32), color: Color, alpha: f32) {
+    if alpha <= 0. || pos.0 < 0 || pos.1 < 0 || pos.0 as usize >= fb_size.0 || pos.1 as usize >= fb_size.1 {
+        return;
+    }
+    let alpha = alpha.min(1.);
+    let index = (pos.1 as usize * fb_size.0 + pos.0 as usize) * 4;
+    for (offset, channel) in color.to_xrgb8888().into_iter().take(3).enumerate() {
+        let dst = fb[index + offset] as f32;
+        fb[index + offset] = (dst + (channel as f32 - dst) * alpha).round() as u8;
+    }
+}
+
+/// Signed distance from `p` to the edge of a rounded rectangle, negative
+/// inside
+fn rounded_rect_distance(p: (f32, f32), center: (f32, f32), half: (f32, f32), radius: f32) -> f32 {
+    let qx = (p.0 - center.0).abs() - (half.0 - radius);
+    let qy = (p.1 - center.1).abs() - (half.1 - radius);
+    let outside = qx.max(0.).hypot(qy.max(0.));
+    let inside = qx.max(qy).min(0.);
+    outside + inside - radius
+}
+
+/// Fill a rectangle with anti-aliased rounded corners and an optional border
+/// of `(width, color)` drawn on the inside of the rectangle
+pub(super) fn fill_rounded_rect(
+    fb: &mut [u8],
+    fb_size: (usize, usize),
+    pos: (f32, f32),
+    size: (f32, f32),
+    radius: f32,
+    fill: Color,
+    border: Option<(f32, Color)>,
+) {
+    if size.0 <= 0. || size.1 <= 0. {
+        return;
+    }
+    let radius = radius.min(size.0 / 2.).min(size.1 / 2.).max(0.);
+    let half = (size.0 / 2., size.1 / 2.);
+    let center = (pos.0 + half.0, pos.1 + half.1);
+
+    let x0 = pos.0.floor().max(0.) as usize;
+    let y0 = pos.1.floor().max(0.) as usize;
+    let x1 = ((pos.0 + size.0).ceil().max(0.) as usize).min(fb_size.0);
+    let y1 = ((pos.1 + size.1).ceil().max(0.) as usize).min(fb_size.1);
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let distance = rounded_rect_distance((x as f32 + 0.5, y as f32 + 0.5), center, half, radius);
+            let coverage = (0.5 - distance).clamp(0., 1.);
+            if coverage <= 0. {
+                continue;
+            }
+
+            let color = match border {
+                Some((width, border_color)) if width > 0. => {
+                    let inner = (0.5 - (distance + width)).clamp(0., 1.);
+                    border_color.mix(fill, inner)
+                },
+                _ => fill,
+            };
+
+            blend_pixel(fb, fb_size, (x as i32, y as i32), color, color.alpha() * coverage);
+        }
+    }
+}
+
+/// Blend an RGBA image over the buffer using its alpha channel
+pub(super) fn blend_rgba_image(fb: &mut [u8], fb_size: (usize, usize), image: &image::RgbaImage, pos: (i32, i32), opacity: f32) {
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a == 0 {
+            continue;
+        }
+        blend_pixel(fb, fb_size, (pos.0 + x as i32, pos.1 + y as i32), Color::rgb(r, g, b), a as f32 / 255. * opacity);
+    }
+}
+
+/// Blend a coverage bitmap (one byte per pixel, as rasterized by fontdue) in
+/// `color` over the buffer. Partially covered pixels are mixed with what is
+/// already there, giving smooth edges
+pub(super) fn blend_coverage_bitmap(
+    fb: &mut [u8],
+    fb_size: (usize, usize),
+    bitmap: &[u8],
+    width: usize,
+    pos: (i32, i32),
+    color: Color,
+    opacity: f32,
+) {
+    let opacity = opacity * color.alpha();
+    if width == 0 || opacity <= 0. {
+        return;
+    }
+    for (i, &coverage) in bitmap.iter().enumerate() {
+        if coverage > 0 {
+            let row = (i / width) as i32;
+            let col = (i % width) as i32;
+            blend_pixel(fb, fb_size, (pos.0 + col, pos.1 + row), color, coverage as f32 / 255. * opacity);
+        }
+    }
+}
+
+/// Grow a coverage bitmap by `radius` pixels in every direction, used to draw
+/// outlines. Returns the new bitmap and its width
+pub(super) fn dilate_coverage(bitmap: &[u8], width: usize, radius: usize) -> (Vec<u8>, usize) {
+    if width == 0 {
+        return (Vec::new(), 0);
+    }
+    let height = bitmap.len() / width;
+    let r = radius as i32;
+    let offsets: Vec<(i32, i32)> = (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .filter(|(dx, dy)| dx * dx + dy * dy <= r * r + r)
+        .collect();
+
+    let out_width = width + 2 * radius;
+    let out_height = height + 2 * radius;
+    let mut out = vec![0; out_width * out_height];
+    for oy in 0..out_height as i32 {
+        for ox in 0..out_width as i32 {
+            let mut max = 0;
+            for (dx, dy) in &offsets {
+                let (x, y) = (ox - r + dx, oy - r + dy);
+                if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                    max = max.max(bitmap[y as usize * width + x as usize]);
+                    if max == 255 {
+                        break;
+                    }
+                }
+            }
+            out[oy as usize * out_width + ox as usize] = max;
+        }
+    }
+    (out, out_width)
+}
@@ -0,0 +1,157 @@
+use std::ops::Range;
+use std::path::Path;
+
+use fontdue::layout::GlyphRasterConfig;
+use fontdue::{Font, FontSettings};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use log::*;
+use ttf_parser::{Face, GlyphId, RasterImageFormat};
+
+use crate::utils::TextSequenceKind;
+
+/// Used when none of the configured fonts can be loaded or have a glyph
+const BUILTIN_TEXT_FONT: &[u8] = include_bytes!("../../assets/fonts/Hack-Regular.ttf");
+const BUILTIN_EMOJI_FONT: &[u8] = include_bytes!("../../assets/fonts/NotoEmoji-Regular.ttf");
+
+/// A full color glyph, scaled to its pixel size
+pub(super) struct ColorGlyph {
+    pub image: RgbaImage,
+    /// Position of the top left corner relative to the pen position on the
+    /// baseline
+    pub offset: (i32, i32),
+}
+
+/// The fonts text is drawn with: a fallback chain for regular text and one
+/// for emoji, each ending with a built-in font
+pub struct FontSet {
+    fonts: Vec<Font>,
+    /// The data of fonts with color bitmaps (CBDT or sbix tables), like Noto
+    /// Color Emoji. fontdue only reads outlines, so these glyphs are drawn
+    /// from the bitmaps instead
+    color_fonts: Vec<Option<Vec<u8>>>,
+    text: Range<usize>,
+    emoji: Range<usize>,
+}
+
+impl FontSet {
+    /// Load the fonts at the given paths, in order of preference. Fonts that
+    /// can't be loaded are skipped with a warning
+    pub fn load(text_fonts: &[impl AsRef<Path>], emoji_fonts: &[impl AsRef<Path>]) -> FontSet {
+        let mut set = FontSet {
+            fonts: Vec::new(),
+            color_fonts: Vec::new(),
+            text: 0..0,
+            emoji: 0..0,
+        };
+
+        set.text = set.load_chain(text_fonts, BUILTIN_TEXT_FONT);
+        set.emoji = set.load_chain(emoji_fonts, BUILTIN_EMOJI_FONT);
+
+        info!("using fonts {:?}", set.fonts);
+
+        set
+    }
+
+    fn load_chain(&mut self, paths: &[impl AsRef<Path>], builtin: &[u8]) -> Range<usize> {
+        let start = self.fonts.len();
+        for path in paths {
+            let path = path.as_ref();
+            let loaded = std::fs::read(path)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| Font::from_bytes(bytes.as_slice(), FontSettings::default())
+                    .map(|font| (font, bytes))
+                    .map_err(|err| err.to_string()));
+            match loaded {
+                Ok((font, bytes)) => {
+                    let has_color = Face::parse(&bytes, 0)
+                        .is_ok_and(|face| face.tables().cbdt.is_some() || face.tables().sbix.is_some());
+                    if has_color {
+                        info!("Using color bitmaps of font {:?}", path);
+                    }
+                    self.fonts.push(font);
+                    self.color_fonts.push(has_color.then_some(bytes));
+                },
+                Err(err) => warn!("Couldn't load font {:?}: {}", path, err),
+            }
+        }
+        self.fonts.push(Font::from_bytes(builtin, FontSettings::default()).expect("the built-in font is valid"));
+        self.color_fonts.push(None);
+        start..self.fonts.len()
+    }
+
+    pub fn as_slice(&self) -> &[Font] {
+        &self.fonts
+    }
+
+    /// The index of the first font in the chain for `kind` that has a glyph
+    /// for `c`, then of the first font in the other chain. `None` when no
+    /// font has it, e.g. for joiners and variation selectors
+    pub fn font_for(&self, c: char, kind: TextSequenceKind) -> Option<usize> {
+        let (preferred, other) = match kind {
+            TextSequenceKind::Text => (&self.text, &self.emoji),
+            TextSequenceKind::Emoji => (&self.emoji, &self.text),
+        };
+        preferred.clone().chain(other.clone())
+            .find(|&index| self.fonts[index].lookup_glyph_index(c) != 0)
+    }
+
+    /// The font used when no font has a glyph
+    pub fn primary(&self, kind: TextSequenceKind) -> usize {
+        match kind {
+            TextSequenceKind::Text => self.text.start,
+            TextSequenceKind::Emoji => self.emoji.start,
+        }
+    }
+
+    pub(super) fn is_color(&self, font_index: usize) -> bool {
+        self.color_fonts[font_index].is_some()
+    }
+
+    /// Decode the color bitmap of a glyph and scale it to `config.px`. `None`
+    /// when the font has no (supported) bitmap for the glyph
+    pub(super) fn rasterize_color(&self, font_index: usize, config: GlyphRasterConfig) -> Option<ColorGlyph> {
+        let data = self.color_fonts[font_index].as_ref()?;
+        let face = Face::parse(data, 0).ok()?;
+        let raster = face.glyph_raster_image(GlyphId(config.glyph_index), config.px.round().max(1.) as u16)?;
+
+        let image = match raster.format {
+            RasterImageFormat::PNG => image::load_from_memory_with_format(raster.data, image::ImageFormat::Png)
+                .inspect_err(|err| debug!("Couldn't decode color glyph {}: {}", config.glyph_index, err))
+                .ok()?
+                .to_rgba8(),
+            RasterImageFormat::BitmapPremulBgra32 => {
+                let (width, height) = (raster.width as u32, raster.height as u32);
+                if raster.data.len() < (width * height * 4) as usize {
+                    return None;
+                }
+                RgbaImage::from_fn(width, height, |x, y| {
+                    let i = ((y * width + x) * 4) as usize;
+                    let [b, g, r, a] = [raster.data[i], raster.data[i + 1], raster.data[i + 2], raster.data[i + 3]];
+                    let unpremultiply = |c: u8| if a == 0 { 0 } else { (c as u32 * 255 / a as u32).min(255) as u8 };
+                    image::Rgba([unpremultiply(r), unpremultiply(g), unpremultiply(b), a])
+                })
+            },
+            _ => return None,
+        };
+
+        // Strikes only exist in a few sizes
+        let scale = config.px / raster.pixels_per_em as f32;
+        let width = ((image.width() as f32 * scale).round() as u32).max(1);
+        let height = ((image.height() as f32 * scale).round() as u32).max(1);
+        let image = if (width, height) == image.dimensions() {
+            image
+        } else {
+            imageops::resize(&image, width, height, FilterType::Triangle)
+        };
+
+        // `raster.y` is the bottom of the image, measured upwards from the
+        // baseline
+        let offset = (
+            (raster.x as f32 * scale).round() as i32,
+            -((raster.y as f32 + raster.height as f32) * scale).round() as i32,
+        );
+
+        Some(ColorGlyph { image, offset })
+    }
+}
@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use fontdue::layout::GlyphRasterConfig;
+use log::*;
+
+use super::draw::dilate_coverage;
+use super::fonts::{ColorGlyph, FontSet};
+
+/// Upper bound on the memory used by cached bitmaps
+const MAX_CACHE_BYTES: usize = 32 * 1024 * 1024;
+
+pub(super) type SharedGlyphCache = Rc<RefCell<GlyphCache>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Variant {
+    /// The coverage bitmap, grown by an outline width (0 for the glyph itself)
+    Coverage { outline: u32 },
+    Color,
+}
+
+/// Font index, glyph and size, and what was rendered
+type GlyphKey = (usize, GlyphRasterConfig, Variant);
+
+enum Bitmap {
+    Coverage { data: Rc<[u8]>, width: usize },
+    /// `None` when the font has no bitmap for the glyph
+    Color(Option<Rc<ColorGlyph>>),
+}
+
+impl Bitmap {
+    fn bytes(&self) -> usize {
+        match self {
+            Bitmap::Coverage { data, .. } => data.len(),
+            Bitmap::Color(glyph) => glyph.as_ref().map_or(0, |glyph| glyph.image.as_raw().len()),
+        }
+    }
+}
+
+struct CachedGlyph {
+    bitmap: Bitmap,
+    last_used: u64,
+}
+
+/// Rasterized glyphs, shared by all text boxes of a `UI`, so text that stays
+/// on screen isn't rasterized again every frame. The least recently used
+/// glyphs are dropped when the cache grows over `MAX_CACHE_BYTES`
+#[derive(Default)]
+pub(super) struct GlyphCache {
+    glyphs: HashMap<GlyphKey, CachedGlyph>,
+    bytes: usize,
+    /// Incremented on every lookup, used to find the least recently used glyphs
+    clock: u64,
+}
+
+impl GlyphCache {
+    /// The coverage bitmap of a glyph and its width. With an `outline` width,
+    /// the bitmap is grown by that many pixels in every direction
+    pub(super) fn get(&mut self, fonts: &FontSet, font_index: usize, config: GlyphRasterConfig, outline: u32) -> (Rc<[u8]>, usize) {
+        let key = (font_index, config, Variant::Coverage { outline });
+        if let Some(Bitmap::Coverage { data, width }) = self.lookup(&key) {
+            return (data.clone(), *width);
+        }
+
+        let (data, width) = if outline == 0 {
+            let (metrics, bitmap) = fonts.as_slice()[font_index].rasterize_config(config);
+            (Rc::<[u8]>::from(bitmap), metrics.width)
+        } else {
+            let (glyph, width) = self.get(fonts, font_index, config, 0);
+            let (bitmap, width) = dilate_coverage(&glyph, width, outline as usize);
+            (Rc::<[u8]>::from(bitmap), width)
+        };
+
+        self.insert(key, Bitmap::Coverage { data: data.clone(), width });
+        (data, width)
+    }
+
+    /// The full color bitmap of a glyph of a color font
+    pub(super) fn get_color(&mut self, fonts: &FontSet, font_index: usize, config: GlyphRasterConfig) -> Option<Rc<ColorGlyph>> {
+        let key = (font_index, config, Variant::Color);
+        if let Some(Bitmap::Color(glyph)) = self.lookup(&key) {
+            return glyph.clone();
+        }
+
+        let glyph = fonts.rasterize_color(font_index, config).map(Rc::new);
+        self.insert(key, Bitmap::Color(glyph.clone()));
+        glyph
+    }
+
+    fn lookup(&mut self, key: &GlyphKey) -> Option<&Bitmap> {
+        self.clock += 1;
+        let glyph = self.glyphs.get_mut(key)?;
+        glyph.last_used = self.clock;
+        Some(&glyph.bitmap)
+    }
+
+    fn insert(&mut self, key: GlyphKey, bitmap: Bitmap) {
+        self.bytes += bitmap.bytes();
+        self.glyphs.insert(key, CachedGlyph {
+            bitmap,
+            last_used: self.clock,
+        });
+        if self.bytes > MAX_CACHE_BYTES {
+            self.evict();
+        }
+    }
+
+    /// Drop the least recently used glyphs until the cache is at three
+    /// quarters of its maximum size
+    fn evict(&mut self) {
+        let mut by_age: Vec<(u64, GlyphKey)> = self.glyphs.iter()
+            .map(|(key, glyph)| (glyph.last_used, *key))
+            .collect();
+        by_age.sort_unstable_by_key(|(last_used, _)| *last_used);
+
+        let target = MAX_CACHE_BYTES / 4 * 3;
+        let before = self.glyphs.len();
+        for (_, key) in by_age {
+            if self.bytes <= target {
+                break;
+            }
+            if let Some(glyph) = self.glyphs.remove(&key) {
+                self.bytes -= glyph.bitmap.bytes();
+            }
+        }
+        debug!("Evicted {} glyphs from the glyph cache", before - self.glyphs.len());
+    }
+}
@@ -0,0 +1,147 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::color::Color;
+use super::{TouchEventListener, UIElement};
+use super::draw::{blend_pixel, blend_rgba_image};
+
+/// How an image is scaled into the rectangle of an `Image` element
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageFit {
+    /// Scale to fit inside the rectangle, keeping the aspect ratio
+    #[default]
+    Contain,
+    /// Scale to fill the rectangle, keeping the aspect ratio and cropping
+    /// what falls outside of it
+    Cover,
+    /// Scale to the size of the rectangle
+    Stretch,
+}
+
+/// Draws an RGBA image, blended using its alpha channel
+pub struct Image {
+    pos: (f32, f32),
+    size: (f32, f32),
+    fit: ImageFit,
+    /// The image scaled to its size on screen
+    scaled: RgbaImage,
+    /// Offset of `scaled` inside the rectangle
+    offset: (i32, i32),
+    /// Scale around the center, applied when drawing
+    scale: f32,
+    opacity: f32,
+    touch_listeners: Vec<TouchEventListener>,
+}
+
+impl Image {
+    pub(super) fn new(image: &RgbaImage, pos: (f32, f32), size: (f32, f32), fit: ImageFit) -> Image {
+        let mut element = Image {
+            pos,
+            size,
+            fit,
+            scaled: RgbaImage::new(0, 0),
+            offset: (0, 0),
+            scale: 1.,
+            opacity: 1.,
+            touch_listeners: Vec::new(),
+        };
+        element.set_image(image);
+        element
+    }
+
+    /// Replace the image. It is scaled once here rather than on every render
+    pub fn set_image(&mut self, image: &RgbaImage) {
+        (self.scaled, self.offset) = fit_image(image, self.size, self.fit);
+    }
+}
+
+/// Scale `image` into a rectangle of `size`. Returns the scaled image and its
+/// offset inside the rectangle
+pub(super) fn fit_image(image: &RgbaImage, size: (f32, f32), fit: ImageFit) -> (RgbaImage, (i32, i32)) {
+    let (width, height) = (size.0.max(0.).round() as u32, size.1.max(0.).round() as u32);
+    if image.width() == 0 || image.height() == 0 || width == 0 || height == 0 {
+        return (RgbaImage::new(0, 0), (0, 0));
+    }
+
+    let scale_x = width as f32 / image.width() as f32;
+    let scale_y = height as f32 / image.height() as f32;
+
+    match fit {
+        ImageFit::Stretch => (imageops::resize(image, width, height, FilterType::Triangle), (0, 0)),
+        ImageFit::Contain => {
+            let scale = scale_x.min(scale_y);
+            let w = ((image.width() as f32 * scale).round() as u32).clamp(1, width);
+            let h = ((image.height() as f32 * scale).round() as u32).clamp(1, height);
+            let scaled = imageops::resize(image, w, h, FilterType::Triangle);
+            (scaled, (((width - w) / 2) as i32, ((height - h) / 2) as i32))
+        },
+        ImageFit::Cover => {
+            let scale = scale_x.max(scale_y);
+            let w = ((image.width() as f32 * scale).round() as u32).max(width);
+            let h = ((image.height() as f32 * scale).round() as u32).max(height);
+            let scaled = imageops::resize(image, w, h, FilterType::Triangle);
+            let cropped = imageops::crop_imm(&scaled, (w - width) / 2, (h - height) / 2, width, height).to_image();
+            (cropped, (0, 0))
+        },
+    }
+}
+
+impl UIElement for Image {
+    fn render(&self, buffer: &mut [u8], buffer_size: (usize, usize)) {
+        let origin = (self.pos.0 + self.offset.0 as f32, self.pos.1 + self.offset.1 as f32);
+        if self.scale == 1. {
+            blend_rgba_image(buffer, buffer_size, &self.scaled, (origin.0 as i32, origin.1 as i32), self.opacity);
+            return;
+        }
+
+        // Nearest neighbour sampling, the image is only scaled while it is
+        // being animated
+        let (width, height) = self.scaled.dimensions();
+        let center = (self.pos.0 + self.size.0 / 2., self.pos.1 + self.size.1 / 2.);
+        let scaled_size = (width as f32 * self.scale, height as f32 * self.scale);
+        let scaled_origin = (
+            center.0 + (origin.0 - center.0) * self.scale,
+            center.1 + (origin.1 - center.1) * self.scale,
+        );
+        for y in 0..scaled_size.1.ceil() as u32 {
+            let src_y = (y as f32 / self.scale) as u32;
+            if src_y >= height {
+                break;
+            }
+            for x in 0..scaled_size.0.ceil() as u32 {
+                let src_x = (x as f32 / self.scale) as u32;
+                if src_x >= width {
+                    break;
+                }
+                let [r, g, b, a] = self.scaled.get_pixel(src_x, src_y).0;
+                let pos = (scaled_origin.0 as i32 + x as i32, scaled_origin.1 as i32 + y as i32);
+                blend_pixel(buffer, buffer_size, pos, Color::rgb(r, g, b), a as f32 / 255. * self.opacity);
+            }
+        }
+    }
+
+    fn add_touch_listener(&mut self, cb: TouchEventListener) {
+        self.touch_listeners.push(cb)
+    }
+
+    fn touch_listeners(&self) -> &Vec<TouchEventListener> {
+        &self.touch_listeners
+    }
+
+    fn is_inside(&self, x: f32, y: f32) -> bool {
+        x >= self.pos.0 && y >= self.pos.1 && x <= self.pos.0 + self.size.0 && y <= self.pos.1 + self.size.1
+    }
+
+    fn set_position(&mut self, pos: (f32, f32)) {
+        self.pos = pos;
+    }
+
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.);
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+}
@@ -0,0 +1,275 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use fontdue::layout::{HorizontalAlign, VerticalAlign};
+
+use crate::color::Color;
+use crate::input::InputAction;
+use super::{Button, ButtonStyle, CountdownRing, ImageFit, TextBox};
+
+/// A position or size, in pixels or as a percentage of the screen size
+/// (`"50%"`)
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(try_from = "LengthRepr")]
+pub enum Length {
+    Pixels(f32),
+    Percent(f32),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum LengthRepr {
+    Number(f32),
+    Text(String),
+}
+
+impl TryFrom<LengthRepr> for Length {
+    type Error = String;
+
+    fn try_from(value: LengthRepr) -> Result<Self, Self::Error> {
+        match value {
+            LengthRepr::Number(pixels) => Ok(Length::Pixels(pixels)),
+            LengthRepr::Text(text) => {
+                let text = text.trim();
+                let parsed = match text.strip_suffix('%') {
+                    Some(percent) => percent.trim().parse().map(Length::Percent),
+                    None => text.strip_suffix("px").unwrap_or(text).trim().parse().map(Length::Pixels),
+                };
+                parsed.map_err(|_| format!("invalid length {:?}, expected a number of pixels or a percentage", text))
+            },
+        }
+    }
+}
+
+impl Default for Length {
+    fn default() -> Self {
+        Length::Pixels(0.)
+    }
+}
+
+impl Length {
+    pub fn full() -> Self {
+        Length::Percent(100.)
+    }
+
+    /// The length in pixels, relative to `total` pixels
+    pub fn resolve(&self, total: f32) -> f32 {
+        match self {
+            Length::Pixels(pixels) => *pixels,
+            Length::Percent(percent) => total * percent / 100.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Align {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VerticalAlignment {
+    Top,
+    #[default]
+    Middle,
+    Bottom,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LayoutElementKind {
+    Text {
+        text: String,
+    },
+    Button {
+        label: String,
+        #[serde(default)]
+        style: ButtonStyle,
+    },
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        fit: ImageFit,
+    },
+    Panel {
+        fill: Color,
+        #[serde(default)]
+        radius: f32,
+    },
+    /// A ring that empties during the countdown, drawn in the largest circle
+    /// fitting the element
+    CountdownRing {
+        #[serde(default = "default_ring_color")]
+        color: Color,
+        #[serde(default = "default_ring_thickness")]
+        thickness: f32,
+    },
+}
+
+fn default_ring_color() -> Color {
+    Color::WHITE
+}
+
+fn default_ring_thickness() -> f32 {
+    20.
+}
+
+/// An element of a screen as described in the configuration. Text and
+/// labels can contain placeholders like `{countdown}`, which are replaced
+/// when the screen is built
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutElement {
+    #[serde(flatten)]
+    pub kind: LayoutElementKind,
+    #[serde(default)]
+    pub x: Length,
+    #[serde(default)]
+    pub y: Length,
+    #[serde(default = "Length::full")]
+    pub width: Length,
+    #[serde(default = "Length::full")]
+    pub height: Length,
+    /// Alignment of text inside the element
+    #[serde(default)]
+    pub align: Align,
+    #[serde(default)]
+    pub vertical_align: VerticalAlignment,
+    /// Defaults to the text size of the screen
+    pub font_size: Option<f32>,
+    /// Text color, defaults to `textColor`
+    pub color: Option<Color>,
+    /// Performed when the element is touched
+    pub action: Option<InputAction>,
+}
+
+impl LayoutElement {
+    /// A text element covering the whole screen
+    pub fn full_screen_text(text: impl Into<String>) -> Self {
+        LayoutElement {
+            kind: LayoutElementKind::Text { text: text.into() },
+            x: Length::default(),
+            y: Length::default(),
+            width: Length::full(),
+            height: Length::full(),
+            align: Align::Center,
+            vertical_align: VerticalAlignment::Middle,
+            font_size: None,
+            color: None,
+            action: None,
+        }
+    }
+
+    /// A countdown ring covering the whole screen
+    pub fn full_screen_countdown_ring() -> Self {
+        LayoutElement {
+            kind: LayoutElementKind::CountdownRing { color: default_ring_color(), thickness: default_ring_thickness() },
+            ..Self::full_screen_text("")
+        }
+    }
+
+    /// A button with the default style
+    pub fn button(label: impl Into<String>) -> Self {
+        LayoutElement {
+            kind: LayoutElementKind::Button { label: label.into(), style: ButtonStyle::default() },
+            ..Self::full_screen_text("")
+        }
+    }
+
+    /// Place the element at `x`, `y` with the given size, all in percent of
+    /// the screen size
+    pub fn with_bounds(mut self, x: f32, y: f32, width: f32, height: f32) -> Self {
+        self.x = Length::Percent(x);
+        self.y = Length::Percent(y);
+        self.width = Length::Percent(width);
+        self.height = Length::Percent(height);
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = Some(font_size);
+        self
+    }
+
+    pub fn with_action(mut self, action: InputAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub(super) fn horizontal_align(&self) -> HorizontalAlign {
+        match self.align {
+            Align::Left => HorizontalAlign::Left,
+            Align::Center => HorizontalAlign::Center,
+            Align::Right => HorizontalAlign::Right,
+        }
+    }
+
+    pub(super) fn vertical_align(&self) -> VerticalAlign {
+        match self.vertical_align {
+            VerticalAlignment::Top => VerticalAlign::Top,
+            VerticalAlignment::Middle => VerticalAlign::Middle,
+            VerticalAlignment::Bottom => VerticalAlign::Bottom,
+        }
+    }
+}
+
+/// Values for the placeholders in a layout, by name without braces
+pub type LayoutVariables = HashMap<String, String>;
+
+/// Replace every `{name}` in `template` by its value
+pub fn fill_template(template: &str, variables: &LayoutVariables) -> String {
+    let mut text = template.to_string();
+    for (name, value) in variables {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+#[derive(Clone)]
+pub(super) enum TemplateTarget {
+    TextBox(Rc<RefCell<TextBox>>),
+    Button(Rc<RefCell<Button>>),
+}
+
+#[derive(Clone)]
+pub(super) struct TemplateText {
+    pub target: TemplateTarget,
+    pub template: String,
+    pub font_size: f32,
+}
+
+/// The elements created from a layout that show text with placeholders, so
+/// the text can be updated when a value changes, and the ones that can be
+/// animated to show progress
+#[derive(Clone, Default)]
+pub struct BuiltLayout {
+    pub(super) texts: Vec<TemplateText>,
+    pub(super) countdown_rings: Vec<Rc<RefCell<CountdownRing>>>,
+}
+
+impl BuiltLayout {
+    pub fn countdown_rings(&self) -> &[Rc<RefCell<CountdownRing>>] {
+        &self.countdown_rings
+    }
+
+    /// Fill in the placeholders again with new values
+    pub fn set_variables(&self, variables: &LayoutVariables) {
+        for text in &self.texts {
+            let filled = fill_template(&text.template, variables);
+            match &text.target {
+                TemplateTarget::TextBox(text_box) => {
+                    let mut text_box = text_box.borrow_mut();
+                    text_box.clear();
+                    text_box.add_text(filled, text.font_size);
+                },
+                TemplateTarget::Button(button) => button.borrow_mut().set_label(filled, text.font_size),
+            }
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+
+use fontdue::layout::{HorizontalAlign, VerticalAlign};
+use ::image::RgbaImage;
+use log::*;
+
+use crate::color::Color;
+use crate::input::{InputAction, InputEvent};
+use glyph_cache::SharedGlyphCache;
+
+mod draw;
+mod fonts;
+mod glyph_cache;
+mod text_box;
+mod target;
+mod button;
+mod image;
+mod panel;
+mod layout;
+mod countdown_ring;
+mod animation;
+mod background;
+
+pub use fonts::*;
+pub use text_box::*;
+pub use target::*;
+pub use button::*;
+pub use image::*;
+pub use panel::*;
+pub use layout::*;
+pub use countdown_ring::*;
+pub use animation::*;
+pub use background::*;
+
+type Fonts = Rc<FontSet>;
+type BoxedUIElement = Box<Rc<RefCell<dyn UIElement>>>;
+
+pub struct UI {
+    elements: Vec<BoxedUIElement>,
+    /// Receive all input events other than taps, which go to the elements
+    gesture_listeners: Vec<GestureListener>,
+    /// Index into `elements` of the element that is currently being touched
+    pressed: Option<usize>,
+    size: (usize, usize),
+    fonts: Fonts,
+    glyph_cache: SharedGlyphCache,
+    touch_events: Receiver<InputEvent>,
+    text_color: Color,
+    text_effects: TextEffects,
+    /// Decoded images used in layouts, by path
+    images: HashMap<PathBuf, Rc<RgbaImage>>,
+    /// Running animations, dropped when they finish or the UI is cleared
+    timelines: Vec<Timeline>,
+    /// Drawn before the elements. Kept when the UI is cleared
+    background: Option<Rc<RefCell<Background>>>,
+}
+
+impl UI {
+    pub fn new(size: (usize, usize), fonts: FontSet) -> (Self, Sender<InputEvent>) {
+        let fonts = Rc::new(fonts);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        (Self {
+            elements: Vec::new(),
+            gesture_listeners: Vec::new(),
+            pressed: None,
+            size,
+            fonts,
+            glyph_cache: SharedGlyphCache::default(),
+            touch_events: rx,
+            text_color: Color::WHITE,
+            text_effects: TextEffects::default(),
+            images: HashMap::new(),
+            timelines: Vec::new(),
+            background: None,
+        }, tx)
+    }
+
+    pub fn set_text_color(&mut self, color: Color) {
+        self.text_color = color;
+    }
+
+    /// Opacity, outline and shadow of text added after this call
+    pub fn set_text_effects(&mut self, effects: TextEffects) {
+        self.text_effects = effects;
+    }
+
+    pub fn add_text_box(&mut self, pos: (f32, f32), size: (f32, f32), hor_align: HorizontalAlign, ver_align: VerticalAlign) -> Rc<RefCell<TextBox>> {
+        let text_box = Rc::new(RefCell::new(
+            TextBox::new(self.fonts.clone(), self.glyph_cache.clone(), pos, size, hor_align, ver_align, self.text_color, self.text_effects)
+        ));
+
+        self.elements.push(Box::new(text_box.clone()));
+
+        return text_box;
+    }
+
+    /// Listen for gestures (swipes, long presses, ...) anywhere on the screen
+    pub fn add_gesture_listener(&mut self, cb: GestureListener) {
+        self.gesture_listeners.push(cb);
+    }
+
+    /// Add a crosshair centered at `center`
+    pub fn add_target(&mut self, center: (f32, f32), size: f32) -> Rc<RefCell<Target>> {
+        let target = Rc::new(RefCell::new(Target {
+            center,
+            size,
+            color: self.text_color,
+            touch_listeners: Vec::new(),
+        }));
+
+        self.elements.push(Box::new(target.clone()));
+
+        return target;
+    }
+
+    /// Add a button with a centered label
+    pub fn add_button(&mut self, pos: (f32, f32), size: (f32, f32), label: impl AsRef<str>, font_size: f32, style: ButtonStyle) -> Rc<RefCell<Button>> {
+        let mut button = Button::new(self.fonts.clone(), self.glyph_cache.clone(), pos, size, style, self.text_color, self.text_effects);
+        button.set_label(label, font_size);
+        let button = Rc::new(RefCell::new(button));
+
+        self.elements.push(Box::new(button.clone()));
+
+        return button;
+    }
+
+    /// Add an image scaled into the rectangle at `pos` of `size`
+    pub fn add_image(&mut self, pos: (f32, f32), size: (f32, f32), image: &RgbaImage, fit: ImageFit) -> Rc<RefCell<Image>> {
+        let image = Rc::new(RefCell::new(Image::new(image, pos, size, fit)));
+
+        self.elements.push(Box::new(image.clone()));
+
+        return image;
+    }
+
+    /// Add a rectangle filled with `color`
+    pub fn add_panel(&mut self, pos: (f32, f32), size: (f32, f32), color: Color, radius: f32) -> Rc<RefCell<Panel>> {
+        let panel = Rc::new(RefCell::new(Panel::new(pos, size, color, radius)));
+
+        self.elements.push(Box::new(panel.clone()));
+
+        return panel;
+    }
+
+    /// Add a ring in `color` that empties as its progress goes from 1 to 0
+    pub fn add_countdown_ring(&mut self, pos: (f32, f32), size: (f32, f32), color: Color, thickness: f32) -> Rc<RefCell<CountdownRing>> {
+        let ring = Rc::new(RefCell::new(CountdownRing::new(pos, size, color, thickness)));
+
+        self.elements.push(Box::new(ring.clone()));
+
+        return ring;
+    }
+
+    /// Draw `background` behind the elements, or nothing when `None`, e.g.
+    /// over the camera preview
+    pub fn set_background(&mut self, background: Option<Rc<RefCell<Background>>>) {
+        self.background = background;
+    }
+
+    /// Start playing a timeline. It is advanced by `animate`
+    pub fn add_timeline(&mut self, timeline: Timeline) {
+        self.timelines.push(timeline);
+    }
+
+    /// Advance the background and all timelines by the time since the last
+    /// frame
+    pub fn animate(&mut self, delta: Duration) {
+        if let Some(background) = &self.background {
+            background.borrow_mut().advance(delta);
+        }
+        for timeline in &mut self.timelines {
+            timeline.advance(delta);
+        }
+        self.timelines.retain(|timeline| !timeline.is_finished());
+    }
+
+    /// Add the elements of a layout. `on_action` is called with the action
+    /// of an element when it is touched
+    pub fn build_layout(
+        &mut self,
+        elements: &[LayoutElement],
+        variables: &LayoutVariables,
+        default_font_size: f32,
+        on_action: Rc<dyn Fn(InputAction)>,
+    ) -> BuiltLayout {
+        let mut built = BuiltLayout::default();
+        let screen = (self.size.0 as f32, self.size.1 as f32);
+
+        for element in elements {
+            let pos = (element.x.resolve(screen.0), element.y.resolve(screen.1));
+            let size = (element.width.resolve(screen.0), element.height.resolve(screen.1));
+            let font_size = element.font_size.unwrap_or(default_font_size);
+
+            let ui_element: Rc<RefCell<dyn UIElement>> = match &element.kind {
+                LayoutElementKind::Text { text } => {
+                    let text_box = self.add_text_box(pos, size, element.horizontal_align(), element.vertical_align());
+                    if let Some(color) = element.color {
+                        text_box.borrow_mut().set_color(color);
+                    }
+                    text_box.borrow_mut().add_text(fill_template(text, variables), font_size);
+                    built.texts.push(TemplateText {
+                        target: TemplateTarget::TextBox(text_box.clone()),
+                        template: text.clone(),
+                        font_size,
+                    });
+                    text_box
+                },
+                LayoutElementKind::Button { label, style } => {
+                    let button = self.add_button(pos, size, fill_template(label, variables), font_size, *style);
+                    if let Some(color) = element.color {
+                        button.borrow_mut().set_text_color(color);
+                    }
+                    built.texts.push(TemplateText {
+                        target: TemplateTarget::Button(button.clone()),
+                        template: label.clone(),
+                        font_size,
+                    });
+                    button
+                },
+                LayoutElementKind::Image { path, fit } => {
+                    let Some(image) = self.load_image(path) else {
+                        continue;
+                    };
+                    self.add_image(pos, size, &image, *fit)
+                },
+                LayoutElementKind::Panel { fill, radius } => self.add_panel(pos, size, *fill, *radius),
+                LayoutElementKind::CountdownRing { color, thickness } => {
+                    let ring = self.add_countdown_ring(pos, size, *color, *thickness);
+                    built.countdown_rings.push(ring.clone());
+                    ring
+                },
+            };
+
+            if let Some(action) = element.action {
+                let on_action = on_action.clone();
+                ui_element.borrow_mut().add_touch_listener(Box::new(move || on_action(action)));
+            }
+        }
+
+        built
+    }
+
+    /// Decode an image, or get it from the images decoded before
+    fn load_image(&mut self, path: &PathBuf) -> Option<Rc<RgbaImage>> {
+        if let Some(image) = self.images.get(path) {
+            return Some(image.clone());
+        }
+        match ::image::open(path) {
+            Ok(image) => {
+                let image = Rc::new(image.to_rgba8());
+                self.images.insert(path.clone(), image.clone());
+                Some(image)
+            },
+            Err(err) => {
+                warn!("Couldn't load image {:?}: {}", path, err);
+                None
+            },
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.elements.clear();
+        self.gesture_listeners.clear();
+        self.timelines.clear();
+        self.pressed = None;
+    }
+
+    pub fn render(&self, buffer: &mut [u8]) {
+        if let Some(background) = &self.background {
+            background.borrow().render(buffer);
+        }
+        for element in self.elements.iter() {
+            element.borrow().render(buffer, self.size)
+        }
+    }
+
+    /// Drop all pending touch events
+    pub fn discard_input(&mut self) {
+        while self.touch_events.try_recv().is_ok() {}
+    }
+
+    pub fn update(&mut self) {
+        while let Ok(event) = self.touch_events.try_recv() {
+            match event {
+                InputEvent::Press { x, y } => self.press(x as f32, y as f32),
+                InputEvent::Release => self.release(),
+                _ => {},
+            }
+
+            let InputEvent::Tap { x, y } = event else {
+                self.gesture_listeners.iter().for_each(|cb| cb(&event));
+                continue;
+            };
+            // Only the topmost element is touched, e.g. a button on top of a
+            // touchable background
+            let touched = self.elements.iter().rev().find(|element| {
+                let element = element.borrow();
+                !element.touch_listeners().is_empty() && element.is_inside(x as f32, y as f32)
+            });
+            if let Some(element) = touched {
+                element.borrow().touch_listeners().iter().for_each(|cb| cb());
+            }
+        }
+    }
+
+    /// Show the topmost touchable element under the finger as pressed
+    fn press(&mut self, x: f32, y: f32) {
+        self.release();
+        self.pressed = self.elements.iter().rposition(|element| {
+            let element = element.borrow();
+            !element.touch_listeners().is_empty() && element.is_inside(x, y)
+        });
+        if let Some(index) = self.pressed {
+            self.elements[index].borrow_mut().set_pressed(true);
+        }
+    }
+
+    fn release(&mut self) {
+        if let Some(index) = self.pressed.take() {
+            self.elements[index].borrow_mut().set_pressed(false);
+        }
+    }
+}
+
+type TouchEventListener = Box<dyn Fn() -> ()>;
+type GestureListener = Box<dyn Fn(&InputEvent) -> ()>;
+
+pub trait UIElement {
+    fn render(&self, buffer: &mut [u8], buffer_size: (usize, usize));
+    fn add_touch_listener(&mut self, cb: TouchEventListener);
+    fn touch_listeners(&self) -> &Vec<TouchEventListener>;
+    fn is_inside(&self, x: f32, y: f32) -> bool;
+    /// Called when a finger goes down on or is lifted from the element
+    fn set_pressed(&mut self, _pressed: bool) {}
+
+    // Properties that can be animated, see `Timeline`. Elements ignore the
+    // ones they don't support
+
+    /// Move the top left corner of the element
+    fn set_position(&mut self, _pos: (f32, f32)) {}
+    /// Scale around the center of the element
+    fn set_scale(&mut self, _scale: f32) {}
+    /// From 0 (invisible) to 1
+    fn set_opacity(&mut self, _opacity: f32) {}
+    /// The main color of the element
+    fn set_color(&mut self, _color: Color) {}
+    /// From 0 to 1, for elements showing progress
+    fn set_progress(&mut self, _progress: f32) {}
+}
@@ -0,0 +1,66 @@
+use crate::color::Color;
+use super::{TouchEventListener, UIElement};
+use super::draw::fill_rounded_rect;
+
+/// A filled, optionally translucent, rectangle. Used as background behind
+/// other elements
+pub struct Panel {
+    pos: (f32, f32),
+    size: (f32, f32),
+    color: Color,
+    radius: f32,
+    scale: f32,
+    opacity: f32,
+    touch_listeners: Vec<TouchEventListener>,
+}
+
+impl Panel {
+    pub(super) fn new(pos: (f32, f32), size: (f32, f32), color: Color, radius: f32) -> Panel {
+        Panel {
+            pos,
+            size,
+            color,
+            radius,
+            scale: 1.,
+            opacity: 1.,
+            touch_listeners: Vec::new(),
+        }
+    }
+}
+
+impl UIElement for Panel {
+    fn render(&self, buffer: &mut [u8], buffer_size: (usize, usize)) {
+        let size = (self.size.0 * self.scale, self.size.1 * self.scale);
+        let pos = (self.pos.0 + (self.size.0 - size.0) / 2., self.pos.1 + (self.size.1 - size.1) / 2.);
+        fill_rounded_rect(buffer, buffer_size, pos, size, self.radius * self.scale, self.color.with_opacity(self.opacity), None);
+    }
+
+    fn add_touch_listener(&mut self, cb: TouchEventListener) {
+        self.touch_listeners.push(cb)
+    }
+
+    fn touch_listeners(&self) -> &Vec<TouchEventListener> {
+        &self.touch_listeners
+    }
+
+    fn is_inside(&self, x: f32, y: f32) -> bool {
+        x >= self.pos.0 && y >= self.pos.1 && x <= self.pos.0 + self.size.0 && y <= self.pos.1 + self.size.1
+    }
+
+    fn set_position(&mut self, pos: (f32, f32)) {
+        self.pos = pos;
+    }
+
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.);
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+
+    /// The fill color
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}
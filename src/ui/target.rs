@@ -0,0 +1,47 @@
+use crate::color::Color;
+use super::{TouchEventListener, UIElement};
+use super::draw::put_pixel;
+
+/// A crosshair, used as target to touch
+pub struct Target {
+    pub(super) center: (f32, f32),
+    pub(super) size: f32,
+    pub(super) color: Color,
+    pub(super) touch_listeners: Vec<TouchEventListener>,
+}
+
+impl UIElement for Target {
+    fn render(&self, buffer: &mut [u8], buffer_size: (usize, usize)) {
+        const THICKNESS: i32 = 3;
+        let (cx, cy) = (self.center.0 as i32, self.center.1 as i32);
+        let half = (self.size / 2.) as i32;
+
+        for offset in -half..=half {
+            for t in -(THICKNESS / 2)..=(THICKNESS / 2) {
+                put_pixel(buffer, buffer_size, (cx + offset, cy + t), self.color);
+                put_pixel(buffer, buffer_size, (cx + t, cy + offset), self.color);
+            }
+        }
+    }
+
+    fn add_touch_listener(&mut self, cb: TouchEventListener) {
+        self.touch_listeners.push(cb)
+    }
+
+    fn touch_listeners(&self) -> &Vec<TouchEventListener> {
+        &self.touch_listeners
+    }
+
+    fn is_inside(&self, x: f32, y: f32) -> bool {
+        let half = self.size / 2.;
+        (x - self.center.0).abs() <= half && (y - self.center.1).abs() <= half
+    }
+
+    fn set_position(&mut self, pos: (f32, f32)) {
+        self.center = (pos.0 + self.size / 2., pos.1 + self.size / 2.);
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}
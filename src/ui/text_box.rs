@@ -0,0 +1,235 @@
+use fontdue::layout::{CoordinateSystem, HorizontalAlign, LayoutSettings, VerticalAlign, TextStyle};
+use log::*;
+
+use crate::color::Color;
+use crate::utils;
+use super::{Fonts, TouchEventListener, UIElement};
+use super::draw::{blend_coverage_bitmap, blend_rgba_image};
+use super::glyph_cache::SharedGlyphCache;
+
+/// An outline drawn around every glyph
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+pub struct TextOutline {
+    /// In pixels
+    pub width: u32,
+    pub color: Color,
+}
+
+/// A copy of the text drawn behind it
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct TextShadow {
+    /// In pixels
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub color: Color,
+    #[serde(default = "TextShadow::default_opacity")]
+    pub opacity: f32,
+}
+
+impl TextShadow {
+    fn default_opacity() -> f32 {
+        0.6
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextEffects {
+    /// From 0 (invisible) to 1
+    pub opacity: f32,
+    pub outline: Option<TextOutline>,
+    pub shadow: Option<TextShadow>,
+}
+
+impl Default for TextEffects {
+    fn default() -> Self {
+        TextEffects {
+            opacity: 1.,
+            outline: None,
+            shadow: None,
+        }
+    }
+}
+
+pub struct TextBox {
+    layout: fontdue::layout::Layout,
+    fonts: Fonts,
+    glyph_cache: SharedGlyphCache,
+    color: Color,
+    effects: TextEffects,
+    /// Text added with `add_text`, as runs of text, font size and font index,
+    /// so it can be laid out again when the position or scale changes
+    runs: Vec<(String, f32, usize)>,
+    pos: (f32, f32),
+    size: (f32, f32),
+    /// Scale around the center of the box
+    scale: f32,
+    /// Multiplied with the opacity of `effects`
+    opacity: f32,
+    touch_listeners: Vec<TouchEventListener>,
+}
+
+impl TextBox {
+    pub(super) fn new(fonts: Fonts, glyph_cache: SharedGlyphCache, pos: (f32, f32), size: (f32, f32), hor_align: HorizontalAlign, ver_align: VerticalAlign, color: Color, effects: TextEffects) -> TextBox {
+        let mut layout = fontdue::layout::Layout::new(CoordinateSystem::PositiveYDown);
+        layout.reset(&LayoutSettings {
+            x: pos.0,
+            y: pos.1,
+            max_width: Some(size.0),
+            max_height: Some(size.1),
+            horizontal_align: hor_align,
+            vertical_align: ver_align,
+            ..Default::default()
+        });
+
+        return TextBox {
+            layout,
+            fonts,
+            glyph_cache,
+            color,
+            effects,
+            runs: Vec::new(),
+            pos,
+            size,
+            scale: 1.,
+            opacity: 1.,
+            touch_listeners: Vec::new()
+        };
+    }
+
+    /// Lay out all text again after the position or scale changed. Scaled
+    /// text is rasterized at its new size
+    fn relayout(&mut self) {
+        let size = (self.size.0 * self.scale, self.size.1 * self.scale);
+        let mut settings = self.layout.settings().clone();
+        settings.x = self.pos.0 + (self.size.0 - size.0) / 2.;
+        settings.y = self.pos.1 + (self.size.1 - size.1) / 2.;
+        settings.max_width = Some(size.0);
+        settings.max_height = Some(size.1);
+        self.layout.reset(&settings);
+        for (text, font_size, font) in &self.runs {
+            self.layout.append(self.fonts.as_slice(), &TextStyle::new(text, font_size * self.scale, *font));
+        }
+    }
+
+    fn append_run(&mut self, text: &str, font_size: f32, font: usize) {
+        self.layout.append(self.fonts.as_slice(), &TextStyle::new(text, font_size * self.scale, font));
+        self.runs.push((text.to_string(), font_size, font));
+    }
+
+    pub fn add_text(&mut self, text: impl AsRef<str>, font_size: f32) {
+        let text = utils::split_emojis(text.as_ref());
+        trace!("text split: {:?}", text);
+
+        for (kind, text) in text.into_iter() {
+            // Split into runs of characters drawn with the same font of the
+            // fallback chain
+            let mut run_start = 0;
+            let mut run_font = None;
+            for (i, c) in text.char_indices() {
+                let Some(font) = self.fonts.font_for(c, kind) else {
+                    continue;
+                };
+                match run_font {
+                    Some(current) if current != font => {
+                        self.append_run(&text[run_start..i], font_size, current);
+                        run_start = i;
+                    },
+                    _ => {},
+                }
+                run_font = Some(font);
+            }
+            let font = run_font.unwrap_or_else(|| self.fonts.primary(kind));
+            self.append_run(&text[run_start..], font_size, font);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.layout.clear();
+        self.runs.clear();
+    }
+
+    pub fn set_effects(&mut self, effects: TextEffects) {
+        self.effects = effects;
+    }
+}
+
+impl UIElement for TextBox {
+    fn render(&self, buffer: &mut [u8], buffer_size: (usize, usize)) {
+        let opacity = (self.effects.opacity * self.opacity).clamp(0., 1.);
+        let mut cache = self.glyph_cache.borrow_mut();
+        let mut glyphs = Vec::new();
+        let mut color_glyphs = Vec::new();
+        for glyph in self.layout.glyphs() {
+            if self.fonts.is_color(glyph.font_index) {
+                if let Some(color_glyph) = cache.get_color(&self.fonts, glyph.font_index, glyph.key) {
+                    // Undo the bounding box offset the layout applied, to get
+                    // the pen position on the baseline
+                    let metrics = self.fonts.as_slice()[glyph.font_index].metrics_indexed(glyph.key.glyph_index, glyph.key.px);
+                    let pen = (glyph.x as i32 - metrics.xmin, glyph.y as i32 + metrics.height as i32 + metrics.ymin);
+                    color_glyphs.push(((pen.0 + color_glyph.offset.0, pen.1 + color_glyph.offset.1), color_glyph));
+                    continue;
+                }
+            }
+            let (bitmap, width) = cache.get(&self.fonts, glyph.font_index, glyph.key, 0);
+            glyphs.push(((glyph.x as i32, glyph.y as i32), width, bitmap, glyph));
+        }
+
+        // Shadows and outlines of all glyphs go below the text, so they
+        // don't overlap neighbouring glyphs
+        if let Some(shadow) = self.effects.shadow {
+            for ((x, y), width, bitmap, _) in &glyphs {
+                let pos = (x + shadow.offset_x, y + shadow.offset_y);
+                blend_coverage_bitmap(buffer, buffer_size, bitmap, *width, pos, shadow.color, opacity * shadow.opacity);
+            }
+        }
+
+        if let Some(outline) = self.effects.outline.filter(|outline| outline.width > 0) {
+            for ((x, y), _, _, glyph) in &glyphs {
+                let (outline_bitmap, outline_width) = cache.get(&self.fonts, glyph.font_index, glyph.key, outline.width);
+                let pos = (x - outline.width as i32, y - outline.width as i32);
+                blend_coverage_bitmap(buffer, buffer_size, &outline_bitmap, outline_width, pos, outline.color, opacity);
+            }
+        }
+
+        for (pos, width, bitmap, _) in &glyphs {
+            blend_coverage_bitmap(buffer, buffer_size, bitmap, *width, *pos, self.color, opacity);
+        }
+
+        for (pos, glyph) in &color_glyphs {
+            blend_rgba_image(buffer, buffer_size, &glyph.image, *pos, opacity);
+        }
+    }
+
+    fn add_touch_listener(&mut self, cb: TouchEventListener) {
+        self.touch_listeners.push(cb)
+    }
+
+    fn touch_listeners(&self) -> &Vec<TouchEventListener> {
+        &self.touch_listeners
+    }
+
+    fn is_inside(&self, x: f32, y: f32) -> bool {
+        let set = self.layout.settings();
+        return x >= set.x && y >= set.y && x <= set.x + set.max_width.unwrap() && y <= set.y + set.max_height.unwrap();
+    }
+
+    fn set_position(&mut self, pos: (f32, f32)) {
+        self.pos = pos;
+        self.relayout();
+    }
+
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.);
+        self.relayout();
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
+    }
+
+    /// The text color
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}